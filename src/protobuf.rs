@@ -1,15 +1,65 @@
+pub mod compat;
+pub mod diff;
+pub mod json_schema;
+pub mod merge;
+pub mod printer;
+pub mod redact;
+pub mod registry;
+pub mod stream;
+pub mod validate;
+
 use anyhow::{Context, Result};
 use itertools::Itertools;
+use printer::{BytesFormat, JsonPrintOptions, MessagePrintOptions, TreePrintOptions};
 use prost::Message;
-use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, ReflectMessage};
+use prost_reflect::{
+    DescriptorPool, DynamicMessage, EnumDescriptor, MessageDescriptor, ReflectMessage,
+    ServiceDescriptor,
+};
+use redact::RedactPatterns;
 use serde_json::de::Deserializer;
 use std::io::Cursor;
 use std::path::Path;
 use std::{fs, path::PathBuf};
 use tempfile::{self, TempDir};
+use validate::{FieldError, FieldErrorKind};
 
 pub trait ProtobufDescriptorLoader {
+    /// build descriptor pool from proto source
+    /// (in-process with protox if the `protox` feature is enabled, otherwise with protoc)
     fn build_protobuf_descriptor(proto_string: &String) -> Result<DescriptorPool> {
+        #[cfg(feature = "protox")]
+        {
+            Self::build_protobuf_descriptor_with_protox(proto_string)
+        }
+        #[cfg(not(feature = "protox"))]
+        {
+            Self::build_protobuf_descriptor_with_protoc(proto_string)
+        }
+    }
+
+    /// build descriptor pool without protoc or temporary files
+    /// (imports of well-known types (google/protobuf/*.proto) are resolved)
+    #[cfg(feature = "protox")]
+    fn build_protobuf_descriptor_with_protox(proto_string: &String) -> Result<DescriptorPool> {
+        use protox::file::{ChainFileResolver, GoogleFileResolver};
+
+        let mut resolver = ChainFileResolver::new();
+        resolver.add(InMemoryFileResolver {
+            name: "temp.proto".to_string(),
+            source: proto_string.clone(),
+        });
+        resolver.add(GoogleFileResolver::new());
+        let mut compiler = protox::Compiler::with_file_resolver(resolver);
+        compiler.include_imports(true);
+        compiler
+            .open_file("temp.proto")
+            .context("Failed to compile protos with protox")?;
+        Ok(compiler.descriptor_pool())
+    }
+
+    /// build descriptor pool with protoc (via tonic-build, proto files are stored in a temporary dir)
+    fn build_protobuf_descriptor_with_protoc(proto_string: &String) -> Result<DescriptorPool> {
         let (tempdir, tempfile) =
             Self::_store_temp_proto_file(&"temp.proto".to_string(), proto_string)
                 .context("on storing temp proto file")?;
@@ -56,6 +106,36 @@ pub trait ProtobufDescriptorLoader {
     }
 }
 
+// single in-memory proto file for protox
+#[cfg(feature = "protox")]
+struct InMemoryFileResolver {
+    name: String,
+    source: String,
+}
+
+#[cfg(feature = "protox")]
+impl protox::file::FileResolver for InMemoryFileResolver {
+    fn resolve_path(&self, path: &Path) -> Option<String> {
+        (path == Path::new(&self.name)).then(|| self.name.clone())
+    }
+    fn open_file(&self, name: &str) -> Result<protox::file::File, protox::Error> {
+        if name == self.name {
+            protox::file::File::from_source(name, &self.source)
+        } else {
+            Err(protox::Error::file_not_found(name))
+        }
+    }
+}
+
+/// request / response types of a rpc method
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodIo {
+    pub input: MessageDescriptor,
+    pub output: MessageDescriptor,
+    pub client_streaming: bool,
+    pub server_streaming: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct ProtobufDescriptor {
     pool: DescriptorPool,
@@ -67,6 +147,25 @@ impl ProtobufDescriptor {
         let pool = ProtobufDescriptor::build_protobuf_descriptor(proto_string)?;
         Ok(ProtobufDescriptor { pool })
     }
+    /// build from proto definitions generated from JSON Schema (see `json_schema::json_schema_to_proto`)
+    pub fn from_json_schema(
+        schema: &serde_json::Value,
+        package: Option<&str>,
+        message_name: &str,
+    ) -> Result<Self> {
+        let proto_string = json_schema::json_schema_to_proto(schema, package, message_name)?;
+        Self::new(&proto_string)
+    }
+    /// load from encoded FileDescriptorSet (ex. descriptor.bin built by protoc or buf)
+    pub fn from_descriptor_set_bytes(bytes: &[u8]) -> Result<Self> {
+        let pool = DescriptorPool::decode(bytes).context("on decoding descriptor bytes")?;
+        Ok(ProtobufDescriptor { pool })
+    }
+    /// load from FileDescriptorSet file (ex. descriptor.bin built by protoc or buf)
+    pub fn from_descriptor_set_file(path: impl AsRef<Path>) -> Result<Self> {
+        let pool = ProtobufDescriptor::_load_protobuf_descriptor(path.as_ref())?;
+        Ok(ProtobufDescriptor { pool })
+    }
     pub fn get_message_names(&self) -> Vec<String> {
         self.pool
             .all_messages()
@@ -79,6 +178,118 @@ impl ProtobufDescriptor {
     pub fn get_message_by_name(&self, message_name: &str) -> Option<MessageDescriptor> {
         self.pool.get_message_by_name(message_name)
     }
+    /// breaking changes from `old` to `new` schema (see `compat::check_compatibility`)
+    pub fn check_compatibility(old: &Self, new: &Self) -> Vec<compat::BreakingChange> {
+        compat::check_compatibility(&old.pool, &new.pool)
+    }
+    pub fn get_services(&self) -> Vec<ServiceDescriptor> {
+        self.pool.services().collect()
+    }
+    pub fn get_service_by_name(&self, service_name: &str) -> Option<ServiceDescriptor> {
+        self.pool.get_service_by_name(service_name)
+    }
+    /// request / response types of the method (service_name is a full name, ex. "pkg.JobService")
+    pub fn get_method_io(&self, service_name: &str, method_name: &str) -> Result<MethodIo> {
+        let service = self
+            .get_service_by_name(service_name)
+            .ok_or(anyhow::anyhow!(
+                "service not found by name: {}",
+                service_name
+            ))?;
+        let method = service
+            .methods()
+            .find(|m| m.name() == method_name)
+            .ok_or(anyhow::anyhow!(
+                "method not found in {}: {}",
+                service_name,
+                method_name
+            ))?;
+        Ok(MethodIo {
+            input: method.input(),
+            output: method.output(),
+            client_streaming: method.is_client_streaming(),
+            server_streaming: method.is_server_streaming(),
+        })
+    }
+    /// find message by unqualified or partially qualified name
+    /// (ex. "Job" or "data.Job" -> "jobworkerp.data.Job")
+    /// error if not found or ambiguous (with candidate names)
+    pub fn find_message(&self, name_fragment: &str) -> Result<MessageDescriptor> {
+        Self::resolve_by_name(
+            self.pool
+                .all_messages()
+                .map(|m| (m.full_name().to_string(), m)),
+            name_fragment,
+            "message",
+        )
+    }
+    /// find enum by unqualified or partially qualified name (same rule as `find_message`)
+    pub fn find_enum(&self, name_fragment: &str) -> Result<EnumDescriptor> {
+        Self::resolve_by_name(
+            self.pool
+                .all_enums()
+                .map(|e| (e.full_name().to_string(), e)),
+            name_fragment,
+            "enum",
+        )
+    }
+    // exact full name > suffix match (at '.' boundary) > case insensitive suffix match
+    fn resolve_by_name<T: Clone>(
+        candidates: impl Iterator<Item = (String, T)>,
+        name_fragment: &str,
+        kind: &str,
+    ) -> Result<T> {
+        let fragment = name_fragment.trim_start_matches('.');
+        let candidates = candidates.collect_vec();
+        let is_suffix = |full_name: &str, fragment: &str| {
+            full_name == fragment || full_name.ends_with(&format!(".{}", fragment))
+        };
+        let mut matched = candidates
+            .iter()
+            .filter(|(n, _)| is_suffix(n.as_str(), fragment))
+            .collect_vec();
+        if matched.is_empty() {
+            let lower = fragment.to_lowercase();
+            matched = candidates
+                .iter()
+                .filter(|(n, _)| is_suffix(n.to_lowercase().as_str(), lower.as_str()))
+                .collect_vec();
+        }
+        if let Some((_, t)) = matched.iter().find(|(n, _)| n == fragment) {
+            return Ok(t.clone());
+        }
+        match matched.as_slice() {
+            [(_, t)] => Ok(t.clone()),
+            [] => Err(anyhow::anyhow!(
+                "{} not found by name: {}",
+                kind,
+                name_fragment
+            )),
+            _ => Err(anyhow::anyhow!(
+                "ambiguous {} name: {} (candidates: {})",
+                kind,
+                name_fragment,
+                matched.iter().map(|(n, _)| n).sorted().join(", ")
+            )),
+        }
+    }
+    /// validate json for the message and report all field errors (paths are JSON pointers)
+    pub fn validate_json(
+        &self,
+        message_name: &str,
+        json: &str,
+    ) -> std::result::Result<(), Vec<FieldError>> {
+        let Some(descriptor) = self.get_message_by_name(message_name) else {
+            return Err(vec![FieldError::new(
+                "",
+                FieldErrorKind::InvalidInput(format!(
+                    "message not found by name: {}",
+                    message_name
+                )),
+            )]);
+        };
+        validate::validate_json(&descriptor, json)
+    }
     pub fn get_message_from_json(
         descriptor: MessageDescriptor,
         json: &str,
@@ -132,6 +343,13 @@ impl ProtobufDescriptor {
             std::any::type_name::<T>()
         ))
     }
+    /// transcode the dynamic message (decoded with this descriptor) to the concrete generated type
+    pub fn transcode_dynamic<T: ReflectMessage + Default>(
+        &self,
+        message: &DynamicMessage,
+    ) -> Result<T> {
+        registry::transcode_dynamic(message)
+    }
     pub fn serialize_message<T: Message>(arg: &T) -> Vec<u8> {
         let mut buf = Vec::with_capacity(arg.encoded_len());
         arg.encode(&mut buf).unwrap();
@@ -144,60 +362,202 @@ impl ProtobufDescriptor {
         let json = serde_json::to_string(&message)?;
         Ok(json)
     }
+    pub fn message_to_json_with_options(
+        message: &DynamicMessage,
+        options: &JsonPrintOptions,
+    ) -> Result<String> {
+        if options.rewrites_bytes() {
+            let value = Self::message_to_json_value(message, options)?;
+            return Ok(if options.pretty {
+                serde_json::to_string_pretty(&value)?
+            } else {
+                serde_json::to_string(&value)?
+            });
+        }
+        let mut buf = Vec::new();
+        if options.pretty {
+            let mut serializer = serde_json::Serializer::pretty(&mut buf);
+            message.serialize_with_options(&mut serializer, &options.serialize_options())?;
+        } else {
+            let mut serializer = serde_json::Serializer::new(&mut buf);
+            message.serialize_with_options(&mut serializer, &options.serialize_options())?;
+        }
+        Ok(String::from_utf8(buf)?)
+    }
+    pub fn message_to_json_value(
+        message: &DynamicMessage,
+        options: &JsonPrintOptions,
+    ) -> Result<serde_json::Value> {
+        let mut value = message
+            .serialize_with_options(serde_json::value::Serializer, &options.serialize_options())?;
+        if options.rewrites_bytes() {
+            printer::rewrite_json_bytes(message, &mut value, options);
+        }
+        Ok(value)
+    }
     pub fn print_dynamic_message(message: &DynamicMessage, byte_to_string: bool) {
         let message_str = Self::dynamic_message_to_string(message, byte_to_string);
         println!("{}", message_str);
     }
+    pub fn print_dynamic_message_with_options(
+        message: &DynamicMessage,
+        options: &MessagePrintOptions,
+    ) {
+        println!(
+            "{}",
+            Self::dynamic_message_to_string_with_options(message, options)
+        );
+    }
+    pub fn print_dynamic_message_tree(message: &DynamicMessage, options: &TreePrintOptions) {
+        println!("{}", Self::dynamic_message_to_tree_string(message, options));
+    }
+    pub fn dynamic_message_to_tree_string(
+        message: &DynamicMessage,
+        options: &TreePrintOptions,
+    ) -> String {
+        printer::message_to_tree_string(message, options)
+    }
     pub fn dynamic_message_to_string(message: &DynamicMessage, byte_to_string: bool) -> String {
-        message
-            .fields()
-            .map(|(field, value)| {
-                format!(
-                    "{}: {}\n",
-                    field.name(),
-                    Self::value_to_string(value, byte_to_string)
-                )
-            })
-            .join("")
-    }
-    fn value_to_string(v: &prost_reflect::Value, byte_to_string: bool) -> String {
+        Self::dynamic_message_to_string_with_options(
+            message,
+            &MessagePrintOptions::from_byte_to_string(byte_to_string),
+        )
+    }
+    pub fn dynamic_message_to_string_with_options(
+        message: &DynamicMessage,
+        options: &MessagePrintOptions,
+    ) -> String {
+        Self::message_to_string_at(
+            message,
+            options,
+            &RedactPatterns::default(),
+            &mut Vec::new(),
+        )
+    }
+    /// `dynamic_message_to_string` with values of fields matched by `patterns` replaced
+    /// (ex. `&["password", "*.token"]`, see `RedactPatterns`)
+    pub fn dynamic_message_to_string_redacted(
+        message: &DynamicMessage,
+        patterns: &[&str],
+    ) -> String {
+        Self::dynamic_message_to_string_redacted_with_options(
+            message,
+            &MessagePrintOptions::default(),
+            &RedactPatterns::new(patterns),
+        )
+    }
+    pub fn dynamic_message_to_string_redacted_with_options(
+        message: &DynamicMessage,
+        options: &MessagePrintOptions,
+        patterns: &RedactPatterns,
+    ) -> String {
+        Self::message_to_string_at(message, options, patterns, &mut Vec::new())
+    }
+    /// json of the message with values of fields matched by `patterns` replaced
+    pub fn message_to_json_redacted(
+        message: &DynamicMessage,
+        options: &JsonPrintOptions,
+        patterns: &RedactPatterns,
+    ) -> Result<String> {
+        let mut value = Self::message_to_json_value(message, options)?;
+        redact::redact_json(
+            message,
+            &mut value,
+            patterns,
+            options.preserve_proto_field_names,
+        );
+        Ok(if options.pretty {
+            serde_json::to_string_pretty(&value)?
+        } else {
+            serde_json::to_string(&value)?
+        })
+    }
+    fn message_to_string_at(
+        message: &DynamicMessage,
+        options: &MessagePrintOptions,
+        patterns: &RedactPatterns,
+        path: &mut Vec<String>,
+    ) -> String {
+        let mut out = printer::CappedOutput::new(options.max_output_length);
+        Self::write_message(message, options, patterns, path, &mut out);
+        out.finish()
+    }
+    // stop rendering as soon as the output exceeds the cap
+    fn write_message(
+        message: &DynamicMessage,
+        options: &MessagePrintOptions,
+        patterns: &RedactPatterns,
+        path: &mut Vec<String>,
+        out: &mut printer::CappedOutput,
+    ) {
+        for (field, value) in message.fields() {
+            if out.is_full() {
+                return;
+            }
+            out.push_str(field.name());
+            out.push_str(": ");
+            path.push(field.name().to_string());
+            if patterns.matches(&path.iter().map(|s| s.as_str()).collect_vec()) {
+                out.push_str(redact::REDACTED);
+            } else {
+                Self::write_value(value, options, patterns, path, out);
+            }
+            path.pop();
+            out.push_str("\n");
+        }
+    }
+    fn write_value(
+        v: &prost_reflect::Value,
+        options: &MessagePrintOptions,
+        patterns: &RedactPatterns,
+        path: &mut Vec<String>,
+        out: &mut printer::CappedOutput,
+    ) {
         match v {
-            prost_reflect::Value::Bool(v) => format!("{}", v),
-            prost_reflect::Value::I32(v) => format!("{}", v),
-            prost_reflect::Value::I64(v) => format!("{}", v),
-            prost_reflect::Value::U32(v) => format!("{}", v),
-            prost_reflect::Value::U64(v) => format!("{}", v),
-            prost_reflect::Value::F32(v) => format!("{}", v),
-            prost_reflect::Value::F64(v) => format!("{}", v),
-            prost_reflect::Value::String(v) => v.to_string(),
-            prost_reflect::Value::Bytes(v) => {
-                if byte_to_string {
-                    format!("{}", String::from_utf8_lossy(v))
-                } else {
-                    format!("{:x?}", v)
-                }
+            prost_reflect::Value::Bool(v) => out.push_str(&v.to_string()),
+            prost_reflect::Value::I32(v) => out.push_str(&v.to_string()),
+            prost_reflect::Value::I64(v) => out.push_str(&v.to_string()),
+            prost_reflect::Value::U32(v) => out.push_str(&v.to_string()),
+            prost_reflect::Value::U64(v) => out.push_str(&v.to_string()),
+            prost_reflect::Value::F32(v) => out.push_str(&v.to_string()),
+            prost_reflect::Value::F64(v) => out.push_str(&v.to_string()),
+            prost_reflect::Value::String(v) => out.push_str(v),
+            prost_reflect::Value::Bytes(v) => out.push_str(&printer::bytes_to_string(
+                v,
+                options.bytes_format,
+                out.bytes_preview(options.max_bytes_preview),
+            )),
+            prost_reflect::Value::EnumNumber(v) => out.push_str(&format!("{:?}[enum]", v)),
+            prost_reflect::Value::Message(v) => {
+                Self::write_message(v, options, patterns, path, out)
             }
-            prost_reflect::Value::EnumNumber(v) => format!("{:?}[enum]", v),
-            prost_reflect::Value::Message(v) => Self::dynamic_message_to_string(v, byte_to_string),
             prost_reflect::Value::List(v) => {
-                let list_str = v
-                    .iter()
-                    .map(|v| Self::value_to_string(v, byte_to_string))
-                    .join(", ");
-                format!("[{}]", list_str)
+                out.push_str("[");
+                for (i, v) in v.iter().enumerate() {
+                    if out.is_full() {
+                        return;
+                    }
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    Self::write_value(v, options, patterns, path, out);
+                }
+                out.push_str("]");
             }
             prost_reflect::Value::Map(hash_map) => {
-                let map_str = hash_map
-                    .iter()
-                    .map(|(k, v)| {
-                        format!(
-                            "{}: {}",
-                            Self::map_key_to_string(k),
-                            Self::value_to_string(v, byte_to_string)
-                        )
-                    })
-                    .join(", ");
-                format!("{{{}}}", map_str)
+                out.push_str("{");
+                for (i, (k, v)) in hash_map.iter().enumerate() {
+                    if out.is_full() {
+                        return;
+                    }
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    out.push_str(&Self::map_key_to_string(k));
+                    out.push_str(": ");
+                    Self::write_value(v, options, patterns, path, out);
+                }
+                out.push_str("}");
             }
         }
     }
@@ -232,6 +592,39 @@ mod tests {
         pub args: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
     }
 
+    #[cfg(feature = "protox")]
+    #[test]
+    fn test_build_protobuf_descriptor_with_protox() -> Result<()> {
+        let proto_string = r#"
+        syntax = "proto3";
+        package jobworkerp.data;
+        import "google/protobuf/timestamp.proto";
+
+        message Job {
+            string id = 1;
+            optional string name = 2;
+            google.protobuf.Timestamp created_at = 3;
+        }
+        "#;
+        let descriptor_pool = ProtobufDescriptorImpl::build_protobuf_descriptor_with_protox(
+            &proto_string.to_string(),
+        )?;
+        let job_descriptor = descriptor_pool
+            .get_message_by_name("jobworkerp.data.Job")
+            .unwrap();
+        assert_eq!(job_descriptor.fields().count(), 3);
+        assert!(descriptor_pool
+            .get_message_by_name("google.protobuf.Timestamp")
+            .is_some());
+        assert!(
+            ProtobufDescriptorImpl::build_protobuf_descriptor_with_protox(
+                &"syntax = \"proto3\"; message {".to_string()
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_load_protobuf_descriptor() -> Result<()> {
         let proto_string = r#"
@@ -368,4 +761,268 @@ message TestArg {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_from_descriptor_set() -> Result<()> {
+        let proto_string = r#"
+        syntax = "proto3";
+        package jobworkerp.data;
+        message Job {
+            string id = 1;
+        }
+        "#;
+        let bytes = ProtobufDescriptor::new(&proto_string.to_string())?
+            .pool
+            .encode_to_vec();
+        let descriptor = ProtobufDescriptor::from_descriptor_set_bytes(&bytes)?;
+        assert!(descriptor
+            .get_message_by_name("jobworkerp.data.Job")
+            .is_some());
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("descriptor.bin");
+        std::fs::write(&path, &bytes)?;
+        let descriptor = ProtobufDescriptor::from_descriptor_set_file(&path)?;
+        assert_eq!(descriptor.get_message_names(), vec!["jobworkerp.data.Job"]);
+
+        assert!(ProtobufDescriptor::from_descriptor_set_bytes(b"invalid").is_err());
+        assert!(ProtobufDescriptor::from_descriptor_set_file(dir.path().join("none.bin")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_message_to_json_with_options() -> Result<()> {
+        let proto_string = r#"
+        syntax = "proto3";
+        package jobworkerp.data;
+        enum Status {
+            UNKNOWN = 0;
+            RUNNING = 1;
+        }
+        message Job {
+            int64 job_id = 1;
+            Status status = 2;
+            string name = 3;
+        }
+        "#;
+        let descriptor = ProtobufDescriptor::new(&proto_string.to_string())?;
+        let message = descriptor.get_message_by_name_from_json(
+            "jobworkerp.data.Job",
+            r#"{"jobId": "1", "status": "RUNNING"}"#,
+        )?;
+        assert_eq!(
+            ProtobufDescriptor::message_to_json_with_options(&message, &Default::default())?,
+            ProtobufDescriptor::message_to_json(&message)?
+        );
+        let options = JsonPrintOptions {
+            emit_defaults: true,
+            int64_as_number: true,
+            enum_as_int: true,
+            preserve_proto_field_names: true,
+            pretty: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            ProtobufDescriptor::message_to_json_with_options(&message, &options)?,
+            r#"{"job_id":1,"status":1,"name":""}"#
+        );
+        assert_eq!(
+            ProtobufDescriptor::message_to_json_value(&message, &JsonPrintOptions::default())?,
+            serde_json::json!({"jobId": "1", "status": "RUNNING"})
+        );
+        let pretty = JsonPrintOptions {
+            pretty: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            ProtobufDescriptor::message_to_json_with_options(&message, &pretty)?,
+            "{\n  \"jobId\": \"1\",\n  \"status\": \"RUNNING\"\n}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_message_to_json_bytes_format() -> Result<()> {
+        let proto_string = r#"
+        syntax = "proto3";
+        message Chunk {
+            bytes data = 1;
+        }
+        message Payload {
+            bytes data = 1;
+            repeated bytes parts = 2;
+            map<string, bytes> attrs = 3;
+            Chunk chunk = 4;
+        }
+        "#;
+        let descriptor = ProtobufDescriptor::new(&proto_string.to_string())?;
+        let message = descriptor.get_message_by_name_from_json(
+            "Payload",
+            r#"{"data": "aGVsbG8=", "parts": ["YWI="], "attrs": {"k": "eHl6"}, "chunk": {"data": "aGk="}}"#,
+        )?;
+        // base64 by default
+        assert_eq!(
+            ProtobufDescriptor::message_to_json_value(&message, &JsonPrintOptions::default())?,
+            serde_json::json!({"data": "aGVsbG8=", "parts": ["YWI="], "attrs": {"k": "eHl6"}, "chunk": {"data": "aGk="}})
+        );
+        let options = JsonPrintOptions {
+            bytes_format: Some(BytesFormat::Utf8Lossy),
+            ..Default::default()
+        };
+        assert_eq!(
+            ProtobufDescriptor::message_to_json_value(&message, &options)?,
+            serde_json::json!({"data": "hello", "parts": ["ab"], "attrs": {"k": "xyz"}, "chunk": {"data": "hi"}})
+        );
+        let options = JsonPrintOptions {
+            max_bytes_preview: Some(2),
+            ..Default::default()
+        };
+        let json = ProtobufDescriptor::message_to_json_with_options(&message, &options)?;
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json)?,
+            serde_json::json!({"data": "aGU=… (5 bytes)", "parts": ["YWI="], "attrs": {"k": "eHk=… (3 bytes)"}, "chunk": {"data": "aGk="}})
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_message_to_string_redacted() -> Result<()> {
+        let proto_string = r#"
+        syntax = "proto3";
+        message Auth {
+            string token = 1;
+            string user = 2;
+        }
+        message Request {
+            string name = 1;
+            string password = 2;
+            Auth auth = 3;
+            repeated Auth backups = 4;
+            string token = 5;
+        }
+        "#;
+        let descriptor = ProtobufDescriptor::new(&proto_string.to_string())?;
+        let message = descriptor.get_message_by_name_from_json(
+            "Request",
+            r#"{"name": "job", "password": "p", "auth": {"token": "t", "user": "u"}, "backups": [{"token": "b"}], "token": "root"}"#,
+        )?;
+        assert_eq!(
+            ProtobufDescriptor::dynamic_message_to_string_redacted(
+                &message,
+                &["password", "*.token"]
+            ),
+            "name: job\npassword: [REDACTED]\nauth: token: [REDACTED]\nuser: u\n\nbackups: [token: [REDACTED]\n]\ntoken: [REDACTED]\n"
+        );
+        let json = ProtobufDescriptor::message_to_json_redacted(
+            &message,
+            &JsonPrintOptions::default(),
+            &RedactPatterns::new(&["password", "*.token"]),
+        )?;
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json)?,
+            serde_json::json!({"name": "job", "password": "[REDACTED]", "auth": {"token": "[REDACTED]", "user": "u"}, "backups": [{"token": "[REDACTED]"}], "token": "[REDACTED]"})
+        );
+        // no patterns
+        assert_eq!(
+            ProtobufDescriptor::dynamic_message_to_string_redacted(&message, &[]),
+            ProtobufDescriptor::dynamic_message_to_string(&message, false)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_method_io() -> Result<()> {
+        let proto_string = r#"
+        syntax = "proto3";
+        package jobworkerp.service;
+        message JobRequest {
+            string name = 1;
+        }
+        message JobResult {
+            string output = 1;
+        }
+        service JobService {
+            rpc Run(JobRequest) returns (JobResult);
+            rpc Listen(JobRequest) returns (stream JobResult);
+        }
+        "#;
+        let descriptor = ProtobufDescriptor::new(&proto_string.to_string())?;
+        let services = descriptor.get_services();
+        assert_eq!(services.len(), 1);
+        assert_eq!(services[0].full_name(), "jobworkerp.service.JobService");
+        assert!(descriptor
+            .get_service_by_name("jobworkerp.service.JobService")
+            .is_some());
+
+        let io = descriptor.get_method_io("jobworkerp.service.JobService", "Run")?;
+        assert_eq!(io.input.full_name(), "jobworkerp.service.JobRequest");
+        assert_eq!(io.output.full_name(), "jobworkerp.service.JobResult");
+        assert!(!io.client_streaming && !io.server_streaming);
+        let io = descriptor.get_method_io("jobworkerp.service.JobService", "Listen")?;
+        assert!(!io.client_streaming && io.server_streaming);
+
+        assert!(descriptor
+            .get_method_io("jobworkerp.service.JobService", "None")
+            .is_err());
+        assert!(descriptor.get_method_io("JobService", "Run").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_message() -> Result<()> {
+        let proto_string = r#"
+        syntax = "proto3";
+
+        package jobworkerp.data;
+
+        enum Priority {
+            PRIORITY_UNSPECIFIED = 0;
+        }
+        message Job {
+            int64 id = 1;
+            message Data {
+                string name = 1;
+            }
+        }
+        message Worker {
+            message Data {
+                string name = 1;
+            }
+        }
+        "#;
+        let descriptor = ProtobufDescriptor::new(&proto_string.to_string())?;
+        assert_eq!(
+            descriptor.find_message("Job")?.full_name(),
+            "jobworkerp.data.Job"
+        );
+        assert_eq!(
+            descriptor.find_message("data.Job")?.full_name(),
+            "jobworkerp.data.Job"
+        );
+        assert_eq!(
+            descriptor.find_message(".jobworkerp.data.Job")?.full_name(),
+            "jobworkerp.data.Job"
+        );
+        assert_eq!(
+            descriptor.find_message("job")?.full_name(),
+            "jobworkerp.data.Job"
+        );
+        assert_eq!(
+            descriptor.find_message("Job.Data")?.full_name(),
+            "jobworkerp.data.Job.Data"
+        );
+        let err = descriptor.find_message("Data").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ambiguous message name: Data (candidates: jobworkerp.data.Job.Data, jobworkerp.data.Worker.Data)"
+        );
+        assert!(descriptor.find_message("Runner").is_err());
+        // not partial word match
+        assert!(descriptor.find_message("ob").is_err());
+        assert_eq!(
+            descriptor.find_enum("Priority")?.full_name(),
+            "jobworkerp.data.Priority"
+        );
+        Ok(())
+    }
 }
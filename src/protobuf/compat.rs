@@ -0,0 +1,259 @@
+use super::printer::kind_name;
+use prost_reflect::{Cardinality, DescriptorPool, FieldDescriptor};
+use std::fmt;
+
+/// incompatible change between two schema versions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BreakingChange {
+    RemovedMessage {
+        message: String,
+    },
+    RemovedField {
+        message: String,
+        field: String,
+        number: u32,
+    },
+    ChangedFieldNumber {
+        message: String,
+        field: String,
+        old_number: u32,
+        new_number: u32,
+    },
+    ChangedFieldType {
+        message: String,
+        field: String,
+        old_type: String,
+        new_type: String,
+    },
+    ChangedCardinality {
+        message: String,
+        field: String,
+        old_cardinality: String,
+        new_cardinality: String,
+    },
+    RemovedEnum {
+        name: String,
+    },
+    RemovedEnumValue {
+        name: String,
+        value: String,
+        number: i32,
+    },
+}
+
+impl fmt::Display for BreakingChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreakingChange::RemovedMessage { message } => write!(f, "message removed: {}", message),
+            BreakingChange::RemovedField {
+                message,
+                field,
+                number,
+            } => write!(f, "field removed: {}.{} ({})", message, field, number),
+            BreakingChange::ChangedFieldNumber {
+                message,
+                field,
+                old_number,
+                new_number,
+            } => write!(
+                f,
+                "field number changed: {}.{} ({} -> {})",
+                message, field, old_number, new_number
+            ),
+            BreakingChange::ChangedFieldType {
+                message,
+                field,
+                old_type,
+                new_type,
+            } => write!(
+                f,
+                "field type changed: {}.{} ({} -> {})",
+                message, field, old_type, new_type
+            ),
+            BreakingChange::ChangedCardinality {
+                message,
+                field,
+                old_cardinality,
+                new_cardinality,
+            } => write!(
+                f,
+                "field cardinality changed: {}.{} ({} -> {})",
+                message, field, old_cardinality, new_cardinality
+            ),
+            BreakingChange::RemovedEnum { name } => write!(f, "enum removed: {}", name),
+            BreakingChange::RemovedEnumValue {
+                name,
+                value,
+                number,
+            } => write!(f, "enum value removed: {}.{} ({})", name, value, number),
+        }
+    }
+}
+
+/// list breaking changes from `old` to `new` (fields are matched by number)
+///
+/// removed messages/enums/fields/enum values, changed field numbers (same name),
+/// changed field types and changed cardinality (singular / repeated / map / required)
+pub fn check_compatibility(old: &DescriptorPool, new: &DescriptorPool) -> Vec<BreakingChange> {
+    let mut changes = Vec::new();
+    for old_message in old.all_messages().filter(|m| !m.is_map_entry()) {
+        let message = old_message.full_name().to_string();
+        let Some(new_message) = new.get_message_by_name(&message) else {
+            changes.push(BreakingChange::RemovedMessage { message });
+            continue;
+        };
+        for old_field in old_message.fields() {
+            let Some(new_field) = new_message.get_field(old_field.number()) else {
+                match new_message.get_field_by_name(old_field.name()) {
+                    Some(renumbered) => changes.push(BreakingChange::ChangedFieldNumber {
+                        message: message.clone(),
+                        field: old_field.name().to_string(),
+                        old_number: old_field.number(),
+                        new_number: renumbered.number(),
+                    }),
+                    None => changes.push(BreakingChange::RemovedField {
+                        message: message.clone(),
+                        field: old_field.name().to_string(),
+                        number: old_field.number(),
+                    }),
+                }
+                continue;
+            };
+            let (old_cardinality, new_cardinality) =
+                (cardinality_name(&old_field), cardinality_name(&new_field));
+            if old_cardinality != new_cardinality {
+                changes.push(BreakingChange::ChangedCardinality {
+                    message: message.clone(),
+                    field: old_field.name().to_string(),
+                    old_cardinality: old_cardinality.to_string(),
+                    new_cardinality: new_cardinality.to_string(),
+                });
+            }
+            let (old_type, new_type) = (type_name(&old_field), type_name(&new_field));
+            // map <-> non-map changes are reported as cardinality
+            if old_type != new_type && old_field.is_map() == new_field.is_map() {
+                changes.push(BreakingChange::ChangedFieldType {
+                    message: message.clone(),
+                    field: old_field.name().to_string(),
+                    old_type,
+                    new_type,
+                });
+            }
+        }
+    }
+    for old_enum in old.all_enums() {
+        let name = old_enum.full_name().to_string();
+        let Some(new_enum) = new.get_enum_by_name(&name) else {
+            changes.push(BreakingChange::RemovedEnum { name });
+            continue;
+        };
+        for value in old_enum.values() {
+            if new_enum.get_value(value.number()).is_none() {
+                changes.push(BreakingChange::RemovedEnumValue {
+                    name: name.clone(),
+                    value: value.name().to_string(),
+                    number: value.number(),
+                });
+            }
+        }
+    }
+    changes
+}
+
+// map fields: key and value types of the map entry (ex. "map<string, int32>")
+fn type_name(field: &FieldDescriptor) -> String {
+    match field.kind().as_message() {
+        Some(entry) if field.is_map() => format!(
+            "map<{}, {}>",
+            kind_name(&entry.map_entry_key_field().kind()),
+            kind_name(&entry.map_entry_value_field().kind())
+        ),
+        _ => kind_name(&field.kind()),
+    }
+}
+
+fn cardinality_name(field: &FieldDescriptor) -> &'static str {
+    if field.is_map() {
+        return "map";
+    }
+    match field.cardinality() {
+        Cardinality::Repeated => "repeated",
+        Cardinality::Required => "required",
+        Cardinality::Optional => "singular",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::protobuf::ProtobufDescriptor;
+    use anyhow::Result;
+
+    #[test]
+    fn test_check_compatibility() -> Result<()> {
+        let old = ProtobufDescriptor::new(
+            &r#"
+            syntax = "proto3";
+            package jobworkerp.data;
+            enum Status {
+                UNKNOWN = 0;
+                RUNNING = 1;
+                DONE = 2;
+            }
+            message Job {
+                int64 id = 1;
+                string name = 2;
+                repeated string tags = 3;
+                int32 priority = 4;
+                Status status = 5;
+                string owner = 6;
+                map<string, int32> counts = 9;
+                map<string, string> labels = 10;
+            }
+            message Worker {
+                string name = 1;
+            }
+            "#
+            .to_string(),
+        )?;
+        let new = ProtobufDescriptor::new(
+            &r#"
+            syntax = "proto3";
+            package jobworkerp.data;
+            enum Status {
+                UNKNOWN = 0;
+                RUNNING = 1;
+            }
+            message Job {
+                int64 id = 1;
+                string tags = 3;
+                int64 priority = 4;
+                Status status = 5;
+                string owner = 7;
+                string description = 8;
+                map<string, string> counts = 9;
+                map<int32, string> labels = 10;
+            }
+            "#
+            .to_string(),
+        )?;
+        assert!(ProtobufDescriptor::check_compatibility(&old, &old).is_empty());
+        let changes: Vec<String> = ProtobufDescriptor::check_compatibility(&old, &new)
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                "field removed: jobworkerp.data.Job.name (2)",
+                "field cardinality changed: jobworkerp.data.Job.tags (repeated -> singular)",
+                "field type changed: jobworkerp.data.Job.priority (int32 -> int64)",
+                "field number changed: jobworkerp.data.Job.owner (6 -> 7)",
+                "field type changed: jobworkerp.data.Job.counts (map<string, int32> -> map<string, string>)",
+                "field type changed: jobworkerp.data.Job.labels (map<string, string> -> map<int32, string>)",
+                "message removed: jobworkerp.data.Worker",
+                "enum value removed: jobworkerp.data.Status.DONE (2)",
+            ]
+        );
+        Ok(())
+    }
+}
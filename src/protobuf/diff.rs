@@ -0,0 +1,159 @@
+use prost_reflect::{DynamicMessage, FieldDescriptor, ReflectMessage, SerializeOptions, Value};
+use serde_json::json;
+
+/// changed field between two messages (values are in protobuf json mapping, None if not set)
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    /// field names joined by '.' (ex. "worker.name")
+    pub path: String,
+    /// JSON pointer with json field names (ex. "/worker/name")
+    pub json_pointer: String,
+    pub old: Option<serde_json::Value>,
+    pub new: Option<serde_json::Value>,
+}
+
+/// list changed fields from `a` to `b` (singular message fields are compared recursively,
+/// repeated and map fields are compared as a whole)
+///
+/// messages of different types are reported as a single change of the root
+pub fn diff_messages(a: &DynamicMessage, b: &DynamicMessage) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    if a.descriptor() != b.descriptor() {
+        changes.push(FieldChange {
+            path: String::new(),
+            json_pointer: String::new(),
+            old: Some(message_to_json(a)),
+            new: Some(message_to_json(b)),
+        });
+    } else {
+        diff_fields(a, b, "", "", &mut changes);
+    }
+    changes
+}
+
+/// convert changes to RFC 6902 JSON Patch (add / remove / replace operations)
+pub fn to_json_patch(changes: &[FieldChange]) -> serde_json::Value {
+    changes
+        .iter()
+        .map(|change| match (&change.old, &change.new) {
+            (None, Some(new)) => json!({"op": "add", "path": change.json_pointer, "value": new}),
+            (Some(_), None) => json!({"op": "remove", "path": change.json_pointer}),
+            (_, new) => json!({"op": "replace", "path": change.json_pointer, "value": new}),
+        })
+        .collect()
+}
+
+fn diff_fields(
+    a: &DynamicMessage,
+    b: &DynamicMessage,
+    path: &str,
+    json_pointer: &str,
+    changes: &mut Vec<FieldChange>,
+) {
+    for field in a.descriptor().fields() {
+        let (a_has, b_has) = (a.has_field(&field), b.has_field(&field));
+        if !a_has && !b_has {
+            continue;
+        }
+        let field_path = if path.is_empty() {
+            field.name().to_string()
+        } else {
+            format!("{}.{}", path, field.name())
+        };
+        let field_pointer = format!(
+            "{}/{}",
+            json_pointer,
+            field.json_name().replace('~', "~0").replace('/', "~1")
+        );
+        let (a_value, b_value) = (a.get_field(&field), b.get_field(&field));
+        if a_has && b_has && !field.is_list() && !field.is_map() {
+            if let (Value::Message(a_message), Value::Message(b_message)) =
+                (a_value.as_ref(), b_value.as_ref())
+            {
+                diff_fields(a_message, b_message, &field_path, &field_pointer, changes);
+                continue;
+            }
+        }
+        if a_has != b_has || a_value != b_value {
+            changes.push(FieldChange {
+                path: field_path,
+                json_pointer: field_pointer,
+                old: a_has.then(|| field_value_to_json(a, &field)),
+                new: b_has.then(|| field_value_to_json(b, &field)),
+            });
+        }
+    }
+}
+
+// json value of a field in protobuf json mapping (same as message_to_json)
+fn field_value_to_json(message: &DynamicMessage, field: &FieldDescriptor) -> serde_json::Value {
+    let mut single = DynamicMessage::new(message.descriptor());
+    single.set_field(field, message.get_field(field).into_owned());
+    let options = SerializeOptions::new().skip_default_fields(false);
+    single
+        .serialize_with_options(serde_json::value::Serializer, &options)
+        .ok()
+        .and_then(|mut v| v.get_mut(field.json_name()).map(serde_json::Value::take))
+        .unwrap_or_default()
+}
+
+fn message_to_json(message: &DynamicMessage) -> serde_json::Value {
+    serde_json::to_value(message).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::ProtobufDescriptor;
+    use anyhow::Result;
+
+    #[test]
+    fn test_diff_messages() -> Result<()> {
+        let proto_string = r#"
+        syntax = "proto3";
+        package jobworkerp.data;
+        message Worker {
+            string name = 1;
+            optional int32 concurrency = 2;
+        }
+        message Job {
+            int64 id = 1;
+            Worker worker = 2;
+            repeated string tags = 3;
+            map<string, int32> counts = 4;
+            string description = 5;
+        }
+        "#;
+        let descriptor = ProtobufDescriptor::new(&proto_string.to_string())?;
+        let to_message =
+            |json: &str| descriptor.get_message_by_name_from_json("jobworkerp.data.Job", json);
+        let a = to_message(
+            r#"{"id": "1", "worker": {"name": "w", "concurrency": 1}, "tags": ["a"],
+                "counts": {"x": 1}, "description": "old"}"#,
+        )?;
+        let b = to_message(
+            r#"{"id": "1", "worker": {"name": "w2"}, "tags": ["a", "b"], "counts": {"x": 1}}"#,
+        )?;
+        assert!(diff_messages(&a, &a.clone()).is_empty());
+
+        let changes = diff_messages(&a, &b);
+        let paths: Vec<&str> = changes.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["worker.name", "worker.concurrency", "tags", "description"]
+        );
+        assert_eq!(
+            to_json_patch(&changes),
+            serde_json::json!([
+                {"op": "replace", "path": "/worker/name", "value": "w2"},
+                {"op": "remove", "path": "/worker/concurrency"},
+                {"op": "replace", "path": "/tags", "value": ["a", "b"]},
+                {"op": "remove", "path": "/description"},
+            ])
+        );
+        let changes = diff_messages(&b, &a);
+        assert_eq!(changes[1].old, None);
+        assert_eq!(changes[1].new, Some(serde_json::json!(1)));
+        Ok(())
+    }
+}
@@ -0,0 +1,534 @@
+use crate::text::TextUtil;
+use anyhow::{anyhow, Result};
+use itertools::Itertools;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashSet};
+
+const INDENT: &str = "  ";
+const STRUCT_PROTO: &str = "google/protobuf/struct.proto";
+const TIMESTAMP_PROTO: &str = "google/protobuf/timestamp.proto";
+
+/// generate proto3 message definitions from JSON Schema (draft-07 subset)
+///
+/// - object with properties: message (nested objects are nested messages)
+/// - object with additionalProperties only: map<string, V>, free-form object: google.protobuf.Struct
+/// - array: repeated (nested arrays are not supported)
+/// - string enum: nested enum with the values as value names to parse json values as is
+///   (`<NAME>_UNSPECIFIED = 0` is added, values must be identifiers unique in the message)
+/// - `$ref` to `#/definitions/*` or `#/$defs/*`: top-level message
+/// - oneOf/anyOf/allOf, multiple types or no type: google.protobuf.Value
+///
+/// fields are numbered in the order of property names, and not required scalar fields are `optional`
+pub fn json_schema_to_proto(
+    schema: &Value,
+    package: Option<&str>,
+    message_name: &str,
+) -> Result<String> {
+    if schema.get("properties").is_none() && json_types(schema) != ["object"] {
+        return Err(anyhow!("root schema must be an object"));
+    }
+    let mut generator = Generator {
+        root: schema,
+        imports: BTreeSet::new(),
+        definitions: Vec::new(),
+        generated_refs: HashSet::new(),
+    };
+    let message = generator.message(message_name, schema, 0)?;
+
+    let mut out = String::from("syntax = \"proto3\";\n\n");
+    if let Some(package) = package {
+        out.push_str(&format!("package {};\n\n", package));
+    }
+    for import in &generator.imports {
+        out.push_str(&format!("import \"{}\";\n", import));
+    }
+    if !generator.imports.is_empty() {
+        out.push('\n');
+    }
+    out.push_str(&message);
+    for definition in &generator.definitions {
+        out.push('\n');
+        out.push_str(definition);
+    }
+    Ok(out)
+}
+
+enum FieldKind {
+    Scalar,
+    Message,
+    Repeated,
+    Map,
+}
+
+struct Generator<'a> {
+    root: &'a Value,
+    imports: BTreeSet<&'static str>,
+    // top-level messages generated from $ref
+    definitions: Vec<String>,
+    generated_refs: HashSet<String>,
+}
+
+impl Generator<'_> {
+    fn message(&mut self, name: &str, schema: &Value, depth: usize) -> Result<String> {
+        let indent = INDENT.repeat(depth);
+        let required: HashSet<&str> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|a| a.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        let mut properties: Vec<(&String, &Value)> = schema
+            .get("properties")
+            .and_then(Value::as_object)
+            .map(|p| p.iter().collect())
+            .unwrap_or_default();
+        properties.sort_by_key(|(k, _)| *k);
+
+        let mut nested = Vec::new();
+        let mut fields = Vec::new();
+        // names in the message scope (fields, nested types and enum values)
+        let mut symbols = HashSet::new();
+        for (i, (property, property_schema)) in properties.into_iter().enumerate() {
+            let field_name = field_name(property);
+            let (type_name, kind) = self.field_type(
+                property,
+                property_schema,
+                &mut nested,
+                &mut symbols,
+                depth + 1,
+            )?;
+            add_symbol(&mut symbols, &field_name, name)?;
+            let label = match kind {
+                FieldKind::Repeated => "repeated ",
+                FieldKind::Scalar if !required.contains(property.as_str()) => "optional ",
+                _ => "",
+            };
+            if let Some(description) = property_schema.get("description").and_then(Value::as_str) {
+                for line in description.lines() {
+                    fields.push(format!("{}{}// {}", indent, INDENT, line));
+                }
+            }
+            let json_name = if default_json_name(&field_name) == *property {
+                String::new()
+            } else {
+                format!(" [json_name = {:?}]", property)
+            };
+            fields.push(format!(
+                "{}{}{}{} {} = {}{};",
+                indent,
+                INDENT,
+                label,
+                type_name,
+                field_name,
+                i + 1,
+                json_name
+            ));
+        }
+        let mut out = format!("{}message {} {{\n", indent, name);
+        for line in nested.iter().chain(fields.iter()) {
+            out.push_str(line);
+            out.push('\n');
+        }
+        out.push_str(&format!("{}}}\n", indent));
+        Ok(out)
+    }
+
+    fn field_type(
+        &mut self,
+        property: &str,
+        schema: &Value,
+        nested: &mut Vec<String>,
+        symbols: &mut HashSet<String>,
+        depth: usize,
+    ) -> Result<(String, FieldKind)> {
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            return Ok((self.reference(reference)?, FieldKind::Message));
+        }
+        if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+            if values.iter().all(Value::is_string) {
+                let name = TextUtil::to_pascal(property);
+                nested.push(enum_definition(&name, values, symbols, depth)?);
+                return Ok((name, FieldKind::Scalar));
+            }
+        }
+        let types = json_types(schema);
+        let composite = ["oneOf", "anyOf", "allOf"]
+            .iter()
+            .any(|k| schema.get(k).is_some());
+        if composite || types.len() > 1 {
+            return Ok((
+                self.well_known("google.protobuf.Value", STRUCT_PROTO),
+                FieldKind::Message,
+            ));
+        }
+        let format = schema.get("format").and_then(Value::as_str);
+        let scalar = |t: &str| Ok((t.to_string(), FieldKind::Scalar));
+        match types.first().copied() {
+            Some("string") => match format {
+                Some("byte") => scalar("bytes"),
+                Some("date-time") => Ok((
+                    self.well_known("google.protobuf.Timestamp", TIMESTAMP_PROTO),
+                    FieldKind::Message,
+                )),
+                _ if schema.get("contentEncoding").and_then(Value::as_str) == Some("base64") => {
+                    scalar("bytes")
+                }
+                _ => scalar("string"),
+            },
+            Some("integer") => match format {
+                Some(f @ ("int32" | "uint32" | "uint64")) => scalar(f),
+                _ => scalar("int64"),
+            },
+            Some("number") => scalar(if format == Some("float") {
+                "float"
+            } else {
+                "double"
+            }),
+            Some("boolean") => scalar("bool"),
+            Some("array") => {
+                let Some(items) = schema.get("items") else {
+                    return Ok((
+                        self.well_known("google.protobuf.Value", STRUCT_PROTO),
+                        FieldKind::Repeated,
+                    ));
+                };
+                match self.field_type(property, items, nested, symbols, depth)? {
+                    (_, FieldKind::Repeated | FieldKind::Map) => Err(anyhow!(
+                        "nested array or map in array is not supported: {}",
+                        property
+                    )),
+                    (type_name, _) => Ok((type_name, FieldKind::Repeated)),
+                }
+            }
+            Some("object") | None if schema.get("properties").is_some() => {
+                let name = TextUtil::to_pascal(property);
+                add_symbol(symbols, &name, property)?;
+                nested.push(self.message(&name, schema, depth)?.trim_end().to_string());
+                Ok((name, FieldKind::Message))
+            }
+            Some("object") => match schema.get("additionalProperties") {
+                Some(value_schema) if value_schema.is_object() => {
+                    match self.field_type(property, value_schema, nested, symbols, depth)? {
+                        (_, FieldKind::Repeated | FieldKind::Map) => Err(anyhow!(
+                            "nested array or map in map is not supported: {}",
+                            property
+                        )),
+                        (type_name, _) => {
+                            Ok((format!("map<string, {}>", type_name), FieldKind::Map))
+                        }
+                    }
+                }
+                _ => Ok((
+                    self.well_known("google.protobuf.Struct", STRUCT_PROTO),
+                    FieldKind::Message,
+                )),
+            },
+            None => Ok((
+                self.well_known("google.protobuf.Value", STRUCT_PROTO),
+                FieldKind::Message,
+            )),
+            Some(t) => Err(anyhow!("unsupported type for {}: {}", property, t)),
+        }
+    }
+
+    // generate top-level message for "#/definitions/Name" or "#/$defs/Name" (once)
+    fn reference(&mut self, reference: &str) -> Result<String> {
+        let definition_name = reference
+            .strip_prefix("#/definitions/")
+            .or_else(|| reference.strip_prefix("#/$defs/"))
+            .ok_or_else(|| anyhow!("unsupported $ref: {}", reference))?;
+        let root = self.root;
+        let schema = root
+            .pointer(&reference[1..])
+            .ok_or_else(|| anyhow!("$ref not found: {}", reference))?;
+        let name = TextUtil::to_pascal(definition_name);
+        if self.generated_refs.insert(name.clone()) {
+            let message = self.message(&name, schema, 0)?;
+            self.definitions.push(message);
+        }
+        Ok(name)
+    }
+
+    fn well_known(&mut self, type_name: &str, import: &'static str) -> String {
+        self.imports.insert(import);
+        type_name.to_string()
+    }
+}
+
+// value names are the json values (json is parsed into the enum as is)
+fn enum_definition(
+    name: &str,
+    values: &[Value],
+    symbols: &mut HashSet<String>,
+    depth: usize,
+) -> Result<String> {
+    let indent = INDENT.repeat(depth);
+    add_symbol(symbols, name, name)?;
+    let unspecified = format!("{}_UNSPECIFIED", TextUtil::to_screaming_snake(name));
+    add_symbol(symbols, &unspecified, name)?;
+    let mut value_keys = HashSet::from([enum_value_key(name, &unspecified)]);
+    let mut out = format!("{}enum {} {{\n", indent, name);
+    out.push_str(&format!("{}{}{} = 0;\n", indent, INDENT, unspecified));
+    let mut number = 0;
+    for value in values.iter().filter_map(Value::as_str).unique() {
+        if !is_identifier(value) {
+            return Err(anyhow!(
+                "enum value of {} is not usable as a protobuf identifier: {:?}",
+                name,
+                value
+            ));
+        }
+        if !value_keys.insert(enum_value_key(name, value)) {
+            return Err(anyhow!(
+                "enum value of {} conflicts with another value ignoring case and prefix: {:?}",
+                name,
+                value
+            ));
+        }
+        add_symbol(symbols, value, name)?;
+        number += 1;
+        out.push_str(&format!("{}{}{} = {};\n", indent, INDENT, value, number));
+    }
+    out.push_str(&format!("{}}}", indent));
+    Ok(out)
+}
+
+// protoc rejects enum values with the same key in proto3
+// (enum name prefix stripped, case and '_' ignored: "STATUS_IN_PROGRESS" == "in_progress")
+fn enum_value_key(enum_name: &str, value: &str) -> String {
+    let prefix: Vec<char> = enum_name
+        .chars()
+        .filter(|c| *c != '_')
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    let mut matched = 0;
+    let mut rest = "";
+    for (i, c) in value.char_indices() {
+        if matched == prefix.len() {
+            rest = &value[i..];
+            break;
+        }
+        if c == '_' {
+            continue;
+        }
+        if c.to_ascii_lowercase() != prefix[matched] {
+            break;
+        }
+        matched += 1;
+    }
+    let rest = rest.trim_start_matches('_');
+    let stripped = if matched == prefix.len() && !rest.is_empty() {
+        rest
+    } else {
+        value
+    };
+    // PascalCase of lowercased words
+    stripped
+        .split('_')
+        .flat_map(|word| {
+            word.chars()
+                .enumerate()
+                .map(|(i, c)| {
+                    if i == 0 {
+                        c.to_ascii_uppercase()
+                    } else {
+                        c.to_ascii_lowercase()
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+// names of fields, nested types and enum values must be unique in a message
+fn add_symbol(symbols: &mut HashSet<String>, symbol: &str, context: &str) -> Result<()> {
+    if symbols.insert(symbol.to_string()) {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "duplicate name in message: {} ({})",
+            symbol,
+            context
+        ))
+    }
+}
+
+// [A-Za-z_][A-Za-z0-9_]*
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// "type" as a list without "null"
+fn json_types(schema: &Value) -> Vec<&str> {
+    match schema.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .filter(|t| *t != "null")
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// snake_case field name with only [a-zA-Z0-9_] (not starting with a digit)
+fn field_name(property: &str) -> String {
+    let name: String = TextUtil::camel_to_snake(property)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("f_{}", name)
+    } else {
+        name
+    }
+}
+
+// json name generated by protoc (ex. "retry_count" -> "retryCount")
+fn default_json_name(field_name: &str) -> String {
+    let mut out = String::new();
+    let mut upper = false;
+    for c in field_name.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            out.push(c.to_ascii_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::ProtobufDescriptor;
+
+    #[test]
+    fn test_json_schema_to_proto() -> Result<()> {
+        let schema = serde_json::json!({
+            "type": "object",
+            "required": ["name"],
+            "properties": {
+                "name": {"type": "string", "description": "job name"},
+                "retryCount": {"type": "integer"},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "status": {"type": "string", "enum": ["running", "done"]},
+                "worker": {"type": "object", "properties": {"id": {"type": "integer", "format": "int32"}}},
+                "labels": {"type": "object", "additionalProperties": {"type": "string"}},
+                "extra": {"type": "object"},
+                "owner": {"$ref": "#/definitions/User"},
+                "Display-Name": {"type": "string"}
+            },
+            "definitions": {
+                "User": {"type": "object", "properties": {"email": {"type": ["string", "null"]}}}
+            }
+        });
+        let proto = json_schema_to_proto(&schema, Some("jobworkerp.plugin"), "PluginArgs")?;
+        assert_eq!(
+            proto,
+            r#"syntax = "proto3";
+
+package jobworkerp.plugin;
+
+import "google/protobuf/struct.proto";
+
+message PluginArgs {
+  enum Status {
+    STATUS_UNSPECIFIED = 0;
+    running = 1;
+    done = 2;
+  }
+  message Worker {
+    optional int32 id = 1;
+  }
+  optional string display_name = 1 [json_name = "Display-Name"];
+  google.protobuf.Struct extra = 2;
+  map<string, string> labels = 3;
+  // job name
+  string name = 4;
+  User owner = 5;
+  optional int64 retry_count = 6;
+  optional Status status = 7;
+  repeated string tags = 8;
+  Worker worker = 9;
+}
+
+message User {
+  optional string email = 1;
+}
+"#
+        );
+        // generated proto is compilable
+        let descriptor = ProtobufDescriptor::new(&proto)?;
+        assert!(descriptor
+            .get_message_by_name("jobworkerp.plugin.PluginArgs")
+            .is_some());
+        // json valid against the schema is parsed into the generated message
+        let instance =
+            r#"{"name": "job", "retryCount": "3", "status": "running", "Display-Name": "Job"}"#;
+        let message =
+            descriptor.get_message_by_name_from_json("jobworkerp.plugin.PluginArgs", instance)?;
+        assert_eq!(
+            ProtobufDescriptor::message_to_json_value(&message, &Default::default())?,
+            serde_json::from_str::<Value>(instance)?
+        );
+
+        // values not usable as enum value names
+        for values in [
+            serde_json::json!(["a.b"]),
+            serde_json::json!(["日本"]),
+            serde_json::json!(["In Progress"]),
+        ] {
+            let schema = serde_json::json!({
+                "type": "object",
+                "properties": {"status": {"type": "string", "enum": values}}
+            });
+            assert!(json_schema_to_proto(&schema, None, "Args").is_err());
+        }
+        // duplicated values are generated once, conflicting names are rejected
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {"status": {"type": "string", "enum": ["done", "done"]}}
+        });
+        let proto = json_schema_to_proto(&schema, None, "Args")?;
+        assert_eq!(proto.matches("done = ").count(), 1);
+        assert!(ProtobufDescriptor::new(&proto).is_ok());
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "status": {"type": "string", "enum": ["done"]},
+                "phase": {"type": "string", "enum": ["done"]}
+            }
+        });
+        assert!(json_schema_to_proto(&schema, None, "Args").is_err());
+        for values in [
+            serde_json::json!(["in_progress", "IN_PROGRESS"]),
+            serde_json::json!(["unspecified"]),
+        ] {
+            let schema = serde_json::json!({
+                "type": "object",
+                "properties": {"status": {"type": "string", "enum": values}}
+            });
+            assert!(json_schema_to_proto(&schema, None, "Args").is_err());
+        }
+        assert_eq!(enum_value_key("Status", "STATUS_IN_PROGRESS"), "InProgress");
+        assert_eq!(enum_value_key("Status", "in_progress"), "InProgress");
+        assert_eq!(enum_value_key("Status", "status"), "Status");
+
+        let nested_array = serde_json::json!({
+            "type": "object",
+            "properties": {"matrix": {"type": "array", "items": {"type": "array"}}}
+        });
+        assert!(json_schema_to_proto(&nested_array, None, "Args").is_err());
+        assert!(
+            json_schema_to_proto(&serde_json::json!({"type": "string"}), None, "Args").is_err()
+        );
+        Ok(())
+    }
+}
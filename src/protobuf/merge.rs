@@ -0,0 +1,185 @@
+use anyhow::{anyhow, Result};
+use prost_reflect::{DynamicMessage, FieldDescriptor, Kind, ReflectMessage, Value};
+
+/// merge `from` into `into` with protobuf merge semantics
+/// (singular fields set in `from` are overwritten, repeated fields are appended,
+/// map entries are inserted and message fields are merged recursively)
+pub fn merge_messages(into: &mut DynamicMessage, from: &DynamicMessage) -> Result<()> {
+    check_same_type(into, from)?;
+    for (field, value) in from.fields() {
+        merge_field(into, &field, value);
+    }
+    Ok(())
+}
+
+/// copy fields in the mask (paths of proto field names, ex. "worker.name") from `source` into `target`
+///
+/// only fields present in `source` are copied (merged with `merge_messages` semantics).
+/// unlike FieldMaskUtil.merge, primitive fields in the mask that are not set in `source`
+/// are left as is in `target` (not cleared)
+pub fn apply_field_mask(
+    target: &mut DynamicMessage,
+    source: &DynamicMessage,
+    mask: &[&str],
+) -> Result<()> {
+    check_same_type(target, source)?;
+    for path in mask {
+        let segments: Vec<&str> = path.split('.').collect();
+        apply_path(target, source, &segments, path)?;
+    }
+    Ok(())
+}
+
+fn apply_path(
+    target: &mut DynamicMessage,
+    source: &DynamicMessage,
+    segments: &[&str],
+    path: &str,
+) -> Result<()> {
+    let Some((name, rest)) = segments.split_first() else {
+        return Err(anyhow!("empty field mask path"));
+    };
+    let descriptor = target.descriptor();
+    let field = descriptor
+        .get_field_by_name(name)
+        .or_else(|| descriptor.get_field_by_json_name(name))
+        .ok_or_else(|| {
+            anyhow!(
+                "field not found in {}: {} (path: {})",
+                descriptor.full_name(),
+                name,
+                path
+            )
+        })?;
+    if rest.is_empty() {
+        if source.has_field(&field) {
+            merge_field(target, &field, &source.get_field(&field));
+        }
+        return Ok(());
+    }
+    if field.is_list() || field.is_map() || !matches!(field.kind(), Kind::Message(_)) {
+        return Err(anyhow!(
+            "invalid field mask path (not a singular message field: {}): {}",
+            name,
+            path
+        ));
+    }
+    if !source.has_field(&field) {
+        return Ok(());
+    }
+    let source_value = source.get_field(&field);
+    match (
+        source_value.as_message(),
+        target.get_field_mut(&field).as_message_mut(),
+    ) {
+        (Some(source_child), Some(target_child)) => {
+            apply_path(target_child, source_child, rest, path)
+        }
+        _ => Err(anyhow!("invalid field mask path: {}", path)),
+    }
+}
+
+fn merge_field(into: &mut DynamicMessage, field: &FieldDescriptor, value: &Value) {
+    match value {
+        Value::List(list) => {
+            if let Value::List(into_list) = into.get_field_mut(field) {
+                into_list.extend(list.iter().cloned());
+            }
+        }
+        Value::Map(map) => {
+            if let Value::Map(into_map) = into.get_field_mut(field) {
+                into_map.extend(map.iter().map(|(k, v)| (k.clone(), v.clone())));
+            }
+        }
+        Value::Message(message) if into.has_field(field) => {
+            if let Value::Message(into_message) = into.get_field_mut(field) {
+                for (f, v) in message.fields() {
+                    merge_field(into_message, &f, v);
+                }
+            }
+        }
+        _ => into.set_field(field, value.clone()),
+    }
+}
+
+fn check_same_type(a: &DynamicMessage, b: &DynamicMessage) -> Result<()> {
+    if a.descriptor() != b.descriptor() {
+        return Err(anyhow!(
+            "message type mismatch: {} and {}",
+            a.descriptor().full_name(),
+            b.descriptor().full_name()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::printer::JsonPrintOptions;
+    use crate::protobuf::ProtobufDescriptor;
+
+    #[test]
+    fn test_merge_and_apply_field_mask() -> Result<()> {
+        let proto_string = r#"
+        syntax = "proto3";
+        package jobworkerp.data;
+        message Worker {
+            string name = 1;
+            repeated string tags = 2;
+        }
+        message Job {
+            int64 id = 1;
+            string name = 2;
+            Worker worker = 3;
+            repeated string tags = 4;
+            map<string, int32> counts = 5;
+            oneof target {
+                string url = 6;
+                Worker target_worker = 7;
+            }
+        }
+        "#;
+        let descriptor = ProtobufDescriptor::new(&proto_string.to_string())?;
+        let to_message =
+            |json: &str| descriptor.get_message_by_name_from_json("jobworkerp.data.Job", json);
+        let to_json = |message: &DynamicMessage| {
+            ProtobufDescriptor::message_to_json_value(message, &JsonPrintOptions::default())
+        };
+        let into = to_message(
+            r#"{"id": "1", "name": "a", "worker": {"name": "w1", "tags": ["x"]},
+                "tags": ["t1"], "counts": {"a": 1, "b": 2}, "targetWorker": {"name": "t"}}"#,
+        )?;
+        let from = to_message(
+            r#"{"name": "b", "worker": {"tags": ["y"]}, "tags": ["t2"], "counts": {"b": 3},
+                "url": "u"}"#,
+        )?;
+
+        let mut merged = into.clone();
+        merge_messages(&mut merged, &from)?;
+        assert_eq!(
+            to_json(&merged)?,
+            serde_json::json!({"id": "1", "name": "b", "worker": {"name": "w1", "tags": ["x", "y"]},
+                "tags": ["t1", "t2"], "counts": {"a": 1, "b": 3}, "url": "u"})
+        );
+
+        let mut masked = into.clone();
+        apply_field_mask(&mut masked, &from, &["name", "worker.tags", "worker.name"])?;
+        assert_eq!(
+            to_json(&masked)?,
+            serde_json::json!({"id": "1", "name": "b", "worker": {"name": "w1", "tags": ["x", "y"]},
+                "tags": ["t1"], "counts": {"a": 1, "b": 2}, "targetWorker": {"name": "t"}})
+        );
+
+        // unset primitive fields in source are not copied
+        let mut masked = into.clone();
+        apply_field_mask(&mut masked, &from, &["id", "worker.name"])?;
+        assert_eq!(to_json(&masked)?, to_json(&into)?);
+
+        let mut masked = into.clone();
+        assert!(apply_field_mask(&mut masked, &from, &["unknown"]).is_err());
+        assert!(apply_field_mask(&mut masked, &from, &["name.value"]).is_err());
+        assert!(apply_field_mask(&mut masked, &from, &["tags.value"]).is_err());
+        Ok(())
+    }
+}
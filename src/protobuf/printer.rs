@@ -0,0 +1,650 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use itertools::Itertools;
+use prost_reflect::{
+    DynamicMessage, FieldDescriptor, Kind, MapKey, ReflectMessage, SerializeOptions, Value,
+};
+
+const ANSI_RESET: &str = "\x1b[0m";
+const ANSI_BOLD: &str = "\x1b[1m";
+const ANSI_DIM: &str = "\x1b[2m";
+const ANSI_CYAN: &str = "\x1b[36m";
+const ANSI_GREEN: &str = "\x1b[32m";
+
+/// rendering format of bytes fields
+/// (for `MessagePrintOptions`, `TreePrintOptions` and `JsonPrintOptions`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BytesFormat {
+    /// debug print of byte array (ex. `[0, 1, ff]`)
+    #[default]
+    Hex,
+    /// decode as utf-8 (invalid sequences are replaced)
+    Utf8Lossy,
+    /// standard base64 (with padding)
+    Base64,
+}
+
+/// options for `ProtobufDescriptor::dynamic_message_to_string_with_options`
+/// (default: print all as before)
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MessagePrintOptions {
+    pub bytes_format: BytesFormat,
+    /// max bytes rendered for each bytes field (rest is omitted with the total length)
+    pub max_bytes_preview: Option<usize>,
+    /// cap of the whole output in chars (rendering stops at the cap and "… (truncated)" is appended)
+    pub max_output_length: Option<usize>,
+}
+
+impl MessagePrintOptions {
+    // compatible with the `byte_to_string` flag
+    pub fn from_byte_to_string(byte_to_string: bool) -> Self {
+        Self {
+            bytes_format: if byte_to_string {
+                BytesFormat::Utf8Lossy
+            } else {
+                BytesFormat::Hex
+            },
+            ..Default::default()
+        }
+    }
+}
+
+/// options for json serialization of messages (mirrors protobuf JsonPrintOptions)
+/// (default: same as serde serialization of DynamicMessage)
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct JsonPrintOptions {
+    /// output fields with default values (ex. `0`, `""`, `[]`)
+    pub emit_defaults: bool,
+    /// output int64/uint64 values as json numbers instead of strings
+    pub int64_as_number: bool,
+    /// output enum values as numbers instead of names
+    pub enum_as_int: bool,
+    /// use field names in proto instead of lowerCamelCase json names
+    pub preserve_proto_field_names: bool,
+    /// pretty print (ignored for json values)
+    pub pretty: bool,
+    /// render bytes fields in this format instead of base64 of the protobuf json mapping
+    /// (for logging: the output may not be parsable as the message)
+    pub bytes_format: Option<BytesFormat>,
+    /// max bytes rendered for each bytes field (rest is omitted with the total length)
+    pub max_bytes_preview: Option<usize>,
+}
+
+impl JsonPrintOptions {
+    pub(crate) fn serialize_options(&self) -> SerializeOptions {
+        SerializeOptions::new()
+            .skip_default_fields(!self.emit_defaults)
+            .stringify_64_bit_integers(!self.int64_as_number)
+            .use_enum_numbers(self.enum_as_int)
+            .use_proto_field_name(self.preserve_proto_field_names)
+    }
+    // bytes fields need to be rewritten after serialization
+    pub(crate) fn rewrites_bytes(&self) -> bool {
+        self.bytes_format.is_some_and(|f| f != BytesFormat::Base64)
+            || self.max_bytes_preview.is_some()
+    }
+}
+
+/// rewrite bytes fields of the serialized json of the message with `options.bytes_format`
+/// (bytes in well-known types with special json representation are left as is)
+pub(crate) fn rewrite_json_bytes(
+    message: &DynamicMessage,
+    json: &mut serde_json::Value,
+    options: &JsonPrintOptions,
+) {
+    let Some(object) = json.as_object_mut() else {
+        return;
+    };
+    for (field, value) in message.fields() {
+        let key = if options.preserve_proto_field_names {
+            field.name()
+        } else {
+            field.json_name()
+        };
+        let Some(field_json) = object.get_mut(key) else {
+            continue;
+        };
+        match (value, field_json) {
+            (Value::List(values), serde_json::Value::Array(items)) => {
+                for (v, item) in values.iter().zip(items.iter_mut()) {
+                    rewrite_json_value(v, item, options);
+                }
+            }
+            (Value::Map(map), serde_json::Value::Object(entries)) => {
+                for (k, v) in map {
+                    if let Some(entry) = entries.get_mut(&map_key_to_json_key(k)) {
+                        rewrite_json_value(v, entry, options);
+                    }
+                }
+            }
+            (v, field_json) => rewrite_json_value(v, field_json, options),
+        }
+    }
+}
+
+fn rewrite_json_value(value: &Value, json: &mut serde_json::Value, options: &JsonPrintOptions) {
+    match value {
+        Value::Bytes(bytes) => {
+            *json = serde_json::Value::String(bytes_to_string(
+                bytes,
+                options.bytes_format.unwrap_or(BytesFormat::Base64),
+                options.max_bytes_preview,
+            ))
+        }
+        Value::Message(m) => rewrite_json_bytes(m, json, options),
+        _ => {}
+    }
+}
+
+pub(crate) fn map_key_to_json_key(key: &MapKey) -> String {
+    match key {
+        MapKey::Bool(v) => v.to_string(),
+        MapKey::I32(v) => v.to_string(),
+        MapKey::I64(v) => v.to_string(),
+        MapKey::U32(v) => v.to_string(),
+        MapKey::U64(v) => v.to_string(),
+        MapKey::String(v) => v.clone(),
+    }
+}
+
+/// options for tree style message rendering (for cli debugging)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreePrintOptions {
+    /// print field numbers after field names (ex. `name (2)`)
+    pub show_field_numbers: bool,
+    /// print proto type annotations (ex. `<int64>`)
+    pub show_types: bool,
+    pub bytes_format: BytesFormat,
+    /// truncate string values longer than this (in chars)
+    pub max_string_length: Option<usize>,
+    /// truncate bytes values longer than this (in bytes)
+    pub max_bytes_length: Option<usize>,
+    /// colorize output with ansi escape sequences
+    pub color: bool,
+}
+
+impl Default for TreePrintOptions {
+    fn default() -> Self {
+        Self {
+            show_field_numbers: true,
+            show_types: true,
+            bytes_format: BytesFormat::Hex,
+            max_string_length: Some(256),
+            max_bytes_length: Some(64),
+            color: false,
+        }
+    }
+}
+
+/// render message as indented tree
+///
+/// ex.
+/// ```text
+/// jobworkerp.data.Job
+/// ├─ id (1) <int64>: 1
+/// └─ tags (4) <repeated string>: [2]
+///    ├─ [0]: "tag1"
+///    └─ [1]: "tag2"
+/// ```
+pub fn message_to_tree_string(message: &DynamicMessage, options: &TreePrintOptions) -> String {
+    let mut out = String::new();
+    out.push_str(&paint(message.descriptor().full_name(), ANSI_BOLD, options));
+    out.push('\n');
+    write_message_fields(&mut out, message, "", options);
+    out
+}
+
+fn write_message_fields(
+    out: &mut String,
+    message: &DynamicMessage,
+    prefix: &str,
+    options: &TreePrintOptions,
+) {
+    let fields = message.fields().collect_vec();
+    let len = fields.len();
+    for (i, (field, value)) in fields.into_iter().enumerate() {
+        let label = field_label(&field, options);
+        let kind = if field.is_map() {
+            map_value_kind(&field)
+        } else {
+            field.kind()
+        };
+        write_node(out, prefix, i + 1 == len, &label, value, &kind, options);
+    }
+}
+
+fn write_node(
+    out: &mut String,
+    prefix: &str,
+    last: bool,
+    label: &str,
+    value: &Value,
+    kind: &Kind,
+    options: &TreePrintOptions,
+) {
+    let (branch, indent) = if last {
+        ("└─ ", "   ")
+    } else {
+        ("├─ ", "│  ")
+    };
+    let child_prefix = format!("{}{}", prefix, indent);
+    match value {
+        Value::Message(m) => {
+            out.push_str(&format!("{}{}{}\n", prefix, branch, label));
+            write_message_fields(out, m, &child_prefix, options);
+        }
+        Value::List(list) => {
+            out.push_str(&format!(
+                "{}{}{}: [{}]\n",
+                prefix,
+                branch,
+                label,
+                list.len()
+            ));
+            let len = list.len();
+            for (i, v) in list.iter().enumerate() {
+                write_node(
+                    out,
+                    &child_prefix,
+                    i + 1 == len,
+                    &format!("[{}]", i),
+                    v,
+                    kind,
+                    options,
+                );
+            }
+        }
+        Value::Map(map) => {
+            out.push_str(&format!(
+                "{}{}{}: {{{}}}\n",
+                prefix,
+                branch,
+                label,
+                map.len()
+            ));
+            let len = map.len();
+            // sort by key for stable output
+            let entries = map
+                .iter()
+                .sorted_by_key(|(k, _)| map_key_to_string(k))
+                .collect_vec();
+            for (i, (k, v)) in entries.into_iter().enumerate() {
+                write_node(
+                    out,
+                    &child_prefix,
+                    i + 1 == len,
+                    &map_key_to_string(k),
+                    v,
+                    kind,
+                    options,
+                );
+            }
+        }
+        v => {
+            out.push_str(&format!(
+                "{}{}{}: {}\n",
+                prefix,
+                branch,
+                label,
+                paint(&scalar_to_string(v, kind, options), ANSI_GREEN, options)
+            ));
+        }
+    }
+}
+
+fn field_label(field: &FieldDescriptor, options: &TreePrintOptions) -> String {
+    let mut label = paint(field.name(), ANSI_CYAN, options);
+    if options.show_field_numbers {
+        label.push_str(&format!(" ({})", field.number()));
+    }
+    if options.show_types {
+        let type_name = if let (true, Kind::Message(entry)) = (field.is_map(), field.kind()) {
+            format!(
+                "map<{}, {}>",
+                kind_name(&entry.map_entry_key_field().kind()),
+                kind_name(&entry.map_entry_value_field().kind())
+            )
+        } else if field.is_list() {
+            format!("repeated {}", kind_name(&field.kind()))
+        } else {
+            kind_name(&field.kind())
+        };
+        label.push(' ');
+        label.push_str(&paint(&format!("<{}>", type_name), ANSI_DIM, options));
+    }
+    label
+}
+
+fn map_value_kind(field: &FieldDescriptor) -> Kind {
+    match field.kind() {
+        Kind::Message(entry) => entry.map_entry_value_field().kind(),
+        k => k,
+    }
+}
+
+pub(crate) fn kind_name(kind: &Kind) -> String {
+    match kind {
+        Kind::Double => "double".to_string(),
+        Kind::Float => "float".to_string(),
+        Kind::Int32 => "int32".to_string(),
+        Kind::Int64 => "int64".to_string(),
+        Kind::Uint32 => "uint32".to_string(),
+        Kind::Uint64 => "uint64".to_string(),
+        Kind::Sint32 => "sint32".to_string(),
+        Kind::Sint64 => "sint64".to_string(),
+        Kind::Fixed32 => "fixed32".to_string(),
+        Kind::Fixed64 => "fixed64".to_string(),
+        Kind::Sfixed32 => "sfixed32".to_string(),
+        Kind::Sfixed64 => "sfixed64".to_string(),
+        Kind::Bool => "bool".to_string(),
+        Kind::String => "string".to_string(),
+        Kind::Bytes => "bytes".to_string(),
+        Kind::Message(m) => m.full_name().to_string(),
+        Kind::Enum(e) => e.full_name().to_string(),
+    }
+}
+
+fn scalar_to_string(v: &Value, kind: &Kind, options: &TreePrintOptions) -> String {
+    match v {
+        Value::String(s) => format!("{:?}", truncate(s, options.max_string_length)),
+        Value::Bytes(b) => match options.bytes_format {
+            // quote to distinguish from other values
+            BytesFormat::Utf8Lossy => format!(
+                "{:?}",
+                bytes_to_string(b, options.bytes_format, options.max_bytes_length)
+            ),
+            _ => bytes_to_string(b, options.bytes_format, options.max_bytes_length),
+        },
+        Value::EnumNumber(n) => match kind.as_enum().and_then(|e| e.get_value(*n)) {
+            Some(ev) => format!("{}({})", ev.name(), n),
+            None => format!("{}", n),
+        },
+        Value::Bool(v) => format!("{}", v),
+        Value::I32(v) => format!("{}", v),
+        Value::I64(v) => format!("{}", v),
+        Value::U32(v) => format!("{}", v),
+        Value::U64(v) => format!("{}", v),
+        Value::F32(v) => format!("{}", v),
+        Value::F64(v) => format!("{}", v),
+        // not scalar (handled by write_node)
+        Value::Message(_) | Value::List(_) | Value::Map(_) => String::new(),
+    }
+}
+
+fn truncate(s: &str, max_chars: Option<usize>) -> String {
+    match max_chars {
+        Some(max) if s.chars().count() > max => {
+            format!(
+                "{}… ({} chars)",
+                s.chars().take(max).collect::<String>(),
+                s.chars().count()
+            )
+        }
+        _ => s.to_string(),
+    }
+}
+
+/// render bytes with the format, omitting bytes over `max_preview` (with the total length)
+pub(crate) fn bytes_to_string(
+    bytes: &[u8],
+    format: BytesFormat,
+    max_preview: Option<usize>,
+) -> String {
+    let shown = match max_preview {
+        Some(max) if bytes.len() > max => &bytes[..max],
+        _ => bytes,
+    };
+    let s = match format {
+        BytesFormat::Hex => format!("{:x?}", shown),
+        BytesFormat::Utf8Lossy => String::from_utf8_lossy(shown).to_string(),
+        BytesFormat::Base64 => STANDARD.encode(shown),
+    };
+    if shown.len() < bytes.len() {
+        format!("{}… ({} bytes)", s, bytes.len())
+    } else {
+        s
+    }
+}
+
+/// output buffer capped at `max_chars` (text after the cap is dropped and rendering can stop early)
+pub(crate) struct CappedOutput {
+    out: String,
+    // chars in out
+    len: usize,
+    max_chars: Option<usize>,
+    truncated: bool,
+}
+
+impl CappedOutput {
+    pub(crate) fn new(max_chars: Option<usize>) -> Self {
+        Self {
+            out: String::new(),
+            len: 0,
+            max_chars,
+            truncated: false,
+        }
+    }
+    pub(crate) fn push_str(&mut self, s: &str) {
+        let Some(max) = self.max_chars else {
+            self.out.push_str(s);
+            return;
+        };
+        if self.truncated {
+            return;
+        }
+        for (i, _) in s.char_indices() {
+            if self.len == max {
+                self.out.push_str(&s[..i]);
+                self.truncated = true;
+                return;
+            }
+            self.len += 1;
+        }
+        self.out.push_str(s);
+    }
+    /// no more text is accepted
+    pub(crate) fn is_full(&self) -> bool {
+        self.truncated
+    }
+    // bytes enough to fill the rest of the output (a byte is rendered in at least 1/4 char)
+    pub(crate) fn bytes_preview(&self, max_bytes_preview: Option<usize>) -> Option<usize> {
+        let rest = self
+            .max_chars
+            .map(|max| (max - self.len).saturating_add(1).saturating_mul(4));
+        match (max_bytes_preview, rest) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+    pub(crate) fn finish(self) -> String {
+        if self.truncated {
+            format!("{}… (truncated)", self.out)
+        } else {
+            self.out
+        }
+    }
+}
+
+fn map_key_to_string(k: &MapKey) -> String {
+    match k {
+        MapKey::Bool(v) => format!("{}", v),
+        MapKey::I32(v) => format!("{}", v),
+        MapKey::I64(v) => format!("{}", v),
+        MapKey::U32(v) => format!("{}", v),
+        MapKey::U64(v) => format!("{}", v),
+        MapKey::String(v) => v.to_string(),
+    }
+}
+
+fn paint(s: &str, code: &str, options: &TreePrintOptions) -> String {
+    if options.color {
+        format!("{}{}{}", code, s, ANSI_RESET)
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::ProtobufDescriptor;
+    use anyhow::Result;
+
+    #[test]
+    fn test_message_to_tree_string() -> Result<()> {
+        let proto_string = r#"
+        syntax = "proto3";
+
+        package jobworkerp.data;
+
+        enum Status {
+            UNKNOWN = 0;
+            RUNNING = 1;
+        }
+        message Worker {
+            string name = 1;
+            map<string, int32> counts = 2;
+        }
+        message Job {
+            int64 id = 1;
+            repeated string tags = 2;
+            Worker worker = 3;
+            Status status = 4;
+            bytes payload = 5;
+        }
+        "#;
+        let descriptor = ProtobufDescriptor::new(&proto_string.to_string())?;
+        let json = r#"
+        {
+            "id": 1,
+            "tags": ["tag1", "tag2"],
+            "worker": {"name": "w", "counts": {"a": 1}},
+            "status": "RUNNING",
+            "payload": "AAECAwQ="
+        }
+        "#;
+        let message = descriptor.get_message_by_name_from_json("jobworkerp.data.Job", json)?;
+        let options = TreePrintOptions {
+            max_bytes_length: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            message_to_tree_string(&message, &options),
+            r#"jobworkerp.data.Job
+├─ id (1) <int64>: 1
+├─ tags (2) <repeated string>: [2]
+│  ├─ [0]: "tag1"
+│  └─ [1]: "tag2"
+├─ worker (3) <jobworkerp.data.Worker>
+│  ├─ name (1) <string>: "w"
+│  └─ counts (2) <map<string, int32>>: {1}
+│     └─ a: 1
+├─ status (4) <jobworkerp.data.Status>: RUNNING(1)
+└─ payload (5) <bytes>: [0, 1]… (5 bytes)
+"#
+        );
+
+        let options = TreePrintOptions {
+            show_field_numbers: false,
+            show_types: false,
+            ..Default::default()
+        };
+        let tree = message_to_tree_string(&message, &options);
+        assert!(tree.contains("├─ id: 1\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bytes_to_string() {
+        let bytes = [0u8, 1, 2, 0xff];
+        assert_eq!(
+            bytes_to_string(&bytes, BytesFormat::Hex, None),
+            "[0, 1, 2, ff]"
+        );
+        assert_eq!(
+            bytes_to_string(&bytes, BytesFormat::Base64, None),
+            "AAEC/w=="
+        );
+        assert_eq!(
+            bytes_to_string(&bytes, BytesFormat::Hex, Some(2)),
+            "[0, 1]… (4 bytes)"
+        );
+        assert_eq!(
+            bytes_to_string("あいう".as_bytes(), BytesFormat::Utf8Lossy, Some(6)),
+            "あい… (9 bytes)"
+        );
+    }
+
+    #[test]
+    fn test_capped_output() {
+        let mut out = CappedOutput::new(Some(5));
+        out.push_str("あい");
+        out.push_str("うえ");
+        assert!(!out.is_full());
+        assert_eq!(out.bytes_preview(None), Some(8));
+        assert_eq!(out.bytes_preview(Some(3)), Some(3));
+        out.push_str("おかき");
+        assert!(out.is_full());
+        out.push_str("く");
+        assert_eq!(out.finish(), "あいうえお… (truncated)");
+
+        let mut out = CappedOutput::new(Some(2));
+        out.push_str("ab");
+        assert!(!out.is_full());
+        assert_eq!(out.finish(), "ab");
+        let mut out = CappedOutput::new(None);
+        out.push_str("abc");
+        assert_eq!(out.bytes_preview(None), None);
+        assert_eq!(out.finish(), "abc");
+    }
+
+    #[test]
+    fn test_message_to_string_with_options() -> Result<()> {
+        let proto_string = r#"
+        syntax = "proto3";
+
+        message Payload {
+            string name = 1;
+            bytes data = 2;
+        }
+        "#;
+        let descriptor = ProtobufDescriptor::new(&proto_string.to_string())?;
+        let data = STANDARD.encode(vec![0u8; 1024]);
+        let json = format!(r#"{{"name": "large", "data": "{}"}}"#, data);
+        let message = descriptor.get_message_by_name_from_json("Payload", &json)?;
+        let options = MessagePrintOptions {
+            bytes_format: BytesFormat::Base64,
+            max_bytes_preview: Some(3),
+            max_output_length: None,
+        };
+        assert_eq!(
+            ProtobufDescriptor::dynamic_message_to_string_with_options(&message, &options),
+            "name: large\ndata: AAAA… (1024 bytes)\n"
+        );
+        let options = MessagePrintOptions {
+            max_output_length: Some(8),
+            ..Default::default()
+        };
+        assert_eq!(
+            ProtobufDescriptor::dynamic_message_to_string_with_options(&message, &options),
+            "name: la… (truncated)"
+        );
+        // large fields are not rendered beyond the cap
+        let options = MessagePrintOptions {
+            max_output_length: Some(16),
+            ..Default::default()
+        };
+        assert_eq!(
+            ProtobufDescriptor::dynamic_message_to_string_with_options(&message, &options),
+            "name: large\ndata… (truncated)"
+        );
+        let options = MessagePrintOptions {
+            max_output_length: Some(20),
+            ..Default::default()
+        };
+        assert_eq!(
+            ProtobufDescriptor::dynamic_message_to_string_with_options(&message, &options),
+            "name: large\ndata: [0… (truncated)"
+        );
+        Ok(())
+    }
+}
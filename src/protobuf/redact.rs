@@ -0,0 +1,161 @@
+use prost_reflect::{DynamicMessage, Value};
+
+/// replacement of redacted field values
+pub const REDACTED: &str = "[REDACTED]";
+
+/// field path patterns to redact in printed messages
+///
+/// - a pattern without '.' matches the field name at any depth (ex. "password")
+/// - a dotted pattern matches the path of proto field names from the root,
+///   '*' matches any single field name and '**' matches any number of field names
+/// - a leading '*' matches any depth (ex. "*.token" matches "token", "auth.token" and "a.auth.token")
+///
+/// elements of repeated fields and values of map fields share the path of the field
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedactPatterns {
+    patterns: Vec<Vec<String>>,
+}
+
+impl RedactPatterns {
+    /// a leading '*' is read as '**', so a bare "*" redacts every field
+    pub fn new(patterns: &[&str]) -> Self {
+        Self {
+            patterns: patterns
+                .iter()
+                .filter(|p| !p.is_empty())
+                .map(|p| {
+                    let mut segments: Vec<String> = p.split('.').map(|s| s.to_string()).collect();
+                    if segments[0] == "*" {
+                        segments[0] = "**".to_string();
+                    }
+                    segments
+                })
+                .collect(),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+    /// `path`: proto field names from the root message
+    pub fn matches(&self, path: &[&str]) -> bool {
+        self.patterns
+            .iter()
+            .any(|pattern| match pattern.as_slice() {
+                [name] if name != "**" => path.last() == Some(&name.as_str()),
+                segments => match_segments(segments, path),
+            })
+    }
+}
+
+// "*": a field name, "**": any number of field names
+fn match_segments(segments: &[String], path: &[&str]) -> bool {
+    match segments.split_first() {
+        None => path.is_empty(),
+        Some((segment, rest)) if segment == "**" => {
+            (0..=path.len()).any(|skip| match_segments(rest, &path[skip..]))
+        }
+        Some((segment, rest)) => path.split_first().is_some_and(|(name, path_rest)| {
+            (segment == "*" || segment == name) && match_segments(rest, path_rest)
+        }),
+    }
+}
+
+/// replace values of matched fields in the serialized json of the message with `REDACTED`
+pub(crate) fn redact_json(
+    message: &DynamicMessage,
+    json: &mut serde_json::Value,
+    patterns: &RedactPatterns,
+    preserve_proto_field_names: bool,
+) {
+    if !patterns.is_empty() {
+        redact_json_at(
+            message,
+            json,
+            patterns,
+            preserve_proto_field_names,
+            &mut Vec::new(),
+        );
+    }
+}
+
+fn redact_json_at(
+    message: &DynamicMessage,
+    json: &mut serde_json::Value,
+    patterns: &RedactPatterns,
+    preserve_proto_field_names: bool,
+    path: &mut Vec<String>,
+) {
+    let Some(object) = json.as_object_mut() else {
+        // well-known types with special json representation
+        return;
+    };
+    for (field, value) in message.fields() {
+        let key = if preserve_proto_field_names {
+            field.name()
+        } else {
+            field.json_name()
+        };
+        let Some(field_json) = object.get_mut(key) else {
+            continue;
+        };
+        path.push(field.name().to_string());
+        if patterns.matches(&path.iter().map(|s| s.as_str()).collect::<Vec<_>>()) {
+            *field_json = serde_json::Value::String(REDACTED.to_string());
+        } else {
+            let mut redact_nested = |v: &Value, j: &mut serde_json::Value| {
+                if let Value::Message(m) = v {
+                    redact_json_at(m, j, patterns, preserve_proto_field_names, path);
+                }
+            };
+            match (value, field_json) {
+                (Value::List(values), serde_json::Value::Array(items)) => {
+                    for (v, item) in values.iter().zip(items.iter_mut()) {
+                        redact_nested(v, item);
+                    }
+                }
+                (Value::Map(map), serde_json::Value::Object(entries)) => {
+                    for (k, v) in map {
+                        if let Some(entry) =
+                            entries.get_mut(&super::printer::map_key_to_json_key(k))
+                        {
+                            redact_nested(v, entry);
+                        }
+                    }
+                }
+                (v, field_json) => redact_nested(v, field_json),
+            }
+        }
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_patterns_matches() {
+        let patterns = RedactPatterns::new(&["password", "*.token", "worker.secret.value"]);
+        assert!(patterns.matches(&["password"]));
+        assert!(patterns.matches(&["user", "password"]));
+        assert!(patterns.matches(&["auth", "token"]));
+        assert!(patterns.matches(&["token"]));
+        assert!(patterns.matches(&["a", "auth", "token"]));
+        assert!(!patterns.matches(&["token", "expires"]));
+        assert!(patterns.matches(&["worker", "secret", "value"]));
+        assert!(!patterns.matches(&["worker", "secret"]));
+        assert!(!patterns.matches(&["name"]));
+
+        let patterns = RedactPatterns::new(&["worker.*.key", "job.**.token"]);
+        assert!(patterns.matches(&["worker", "auth", "key"]));
+        assert!(!patterns.matches(&["worker", "key"]));
+        assert!(!patterns.matches(&["worker", "a", "b", "key"]));
+        assert!(patterns.matches(&["job", "token"]));
+        assert!(patterns.matches(&["job", "a", "b", "token"]));
+        assert!(!patterns.matches(&["worker", "token"]));
+        assert!(RedactPatterns::new(&["*"]).matches(&["name"]));
+        assert!(RedactPatterns::new(&["*"]).matches(&["worker", "name"]));
+        assert!(RedactPatterns::new(&[""]).is_empty());
+        assert!(!RedactPatterns::default().matches(&["password"]));
+    }
+}
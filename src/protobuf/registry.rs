@@ -0,0 +1,134 @@
+use anyhow::{anyhow, Context, Result};
+use prost_reflect::{DynamicMessage, ReflectMessage};
+use std::any::Any;
+use std::collections::HashMap;
+
+/// registry of compiled (generated) message types by full name,
+/// to convert dynamic messages decoded at runtime to concrete types
+pub trait MessageTypeRegistry {
+    /// register the type `T` by the full name of its descriptor
+    fn register<T: ReflectMessage + Default + Send + 'static>(&mut self) -> &mut Self
+    where
+        Self: Sized;
+    /// transcode the message to the registered type for its full name
+    fn transcode_any(&self, message: &DynamicMessage) -> Result<Box<dyn Any + Send>>;
+
+    /// transcode the message to the registered type `T`
+    fn transcode_as<T: 'static>(&self, message: &DynamicMessage) -> Result<T>
+    where
+        Self: Sized,
+    {
+        self.transcode_any(message)?
+            .downcast::<T>()
+            .map(|t| *t)
+            .map_err(|_| {
+                anyhow!(
+                    "registered type for {} is not {}",
+                    message.descriptor().full_name(),
+                    std::any::type_name::<T>()
+                )
+            })
+    }
+}
+
+type Transcoder = Box<dyn Fn(&DynamicMessage) -> Result<Box<dyn Any + Send>> + Send + Sync>;
+
+#[derive(Default)]
+pub struct TypeRegistry {
+    transcoders: HashMap<String, Transcoder>,
+}
+
+impl TypeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn contains(&self, full_name: &str) -> bool {
+        self.transcoders.contains_key(full_name)
+    }
+    pub fn registered_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.transcoders.keys().map(|k| k.as_str()).collect();
+        names.sort();
+        names
+    }
+}
+
+impl MessageTypeRegistry for TypeRegistry {
+    fn register<T: ReflectMessage + Default + Send + 'static>(&mut self) -> &mut Self {
+        let full_name = T::default().descriptor().full_name().to_string();
+        self.transcoders.insert(
+            full_name,
+            Box::new(|message: &DynamicMessage| {
+                let t: T = transcode_dynamic(message)?;
+                Ok(Box::new(t) as Box<dyn Any + Send>)
+            }),
+        );
+        self
+    }
+    fn transcode_any(&self, message: &DynamicMessage) -> Result<Box<dyn Any + Send>> {
+        let descriptor = message.descriptor();
+        let transcoder = self
+            .transcoders
+            .get(descriptor.full_name())
+            .ok_or_else(|| anyhow!("message type not registered: {}", descriptor.full_name()))?;
+        transcoder(message)
+    }
+}
+
+/// transcode the dynamic message to the concrete type (error if full names differ)
+pub fn transcode_dynamic<T: ReflectMessage + Default>(message: &DynamicMessage) -> Result<T> {
+    let expected = T::default().descriptor();
+    if expected.full_name() != message.descriptor().full_name() {
+        return Err(anyhow!(
+            "message type mismatch: expected {}, actual {}",
+            expected.full_name(),
+            message.descriptor().full_name()
+        ));
+    }
+    message.transcode_to::<T>().context(format!(
+        "on transcoding dynamic message to {}",
+        std::any::type_name::<T>()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::ProtobufDescriptor;
+    use prost_reflect::prost_types::{Duration, Timestamp};
+
+    #[test]
+    fn test_transcode_dynamic() -> Result<()> {
+        let proto_string = r#"
+        syntax = "proto3";
+        package jobworkerp.data;
+        import "google/protobuf/timestamp.proto";
+        message Job {
+            google.protobuf.Timestamp created_at = 1;
+        }
+        "#;
+        let descriptor = ProtobufDescriptor::new(&proto_string.to_string())?;
+        let job = descriptor.get_message_by_name_from_json(
+            "jobworkerp.data.Job",
+            r#"{"createdAt": "1970-01-01T00:00:10Z"}"#,
+        )?;
+        let created_at = job
+            .get_field_by_name("created_at")
+            .and_then(|v| v.as_message().cloned())
+            .unwrap();
+        let timestamp: Timestamp = descriptor.transcode_dynamic(&created_at)?;
+        assert_eq!(timestamp.seconds, 10);
+        assert!(descriptor.transcode_dynamic::<Timestamp>(&job).is_err());
+
+        let mut registry = TypeRegistry::new();
+        registry.register::<Timestamp>().register::<Duration>();
+        assert_eq!(
+            registry.registered_names(),
+            vec!["google.protobuf.Duration", "google.protobuf.Timestamp"]
+        );
+        let timestamp: Timestamp = registry.transcode_as(&created_at)?;
+        assert_eq!(timestamp.seconds, 10);
+        assert!(registry.transcode_as::<Duration>(&created_at).is_err());
+        assert!(registry.transcode_any(&job).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+use super::printer::JsonPrintOptions;
+use super::ProtobufDescriptor;
+use anyhow::{anyhow, Context, Result};
+use prost::Message;
+use prost_reflect::{DynamicMessage, MessageDescriptor};
+use std::io::{BufRead, ErrorKind, Read, Write};
+
+/// max size of a length-delimited record (guard for broken input)
+pub const MAX_RECORD_SIZE: usize = 64 * 1024 * 1024;
+
+/// convert NDJSON records to length-delimited protobuf messages one at a time
+/// (empty lines are skipped, returns the number of records)
+pub fn ndjson_to_length_delimited<R: BufRead, W: Write>(
+    descriptor: &MessageDescriptor,
+    reader: R,
+    writer: &mut W,
+) -> Result<usize> {
+    let mut count = 0;
+    let mut buf = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.context(format!("on reading line {}", i + 1))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut deserializer = serde_json::Deserializer::from_str(&line);
+        let message = DynamicMessage::deserialize(descriptor.clone(), &mut deserializer)
+            .and_then(|m| deserializer.end().map(|_| m))
+            .context(format!(
+                "on parsing line {} as {}",
+                i + 1,
+                descriptor.full_name()
+            ))?;
+        buf.clear();
+        message.encode_length_delimited(&mut buf)?;
+        writer.write_all(&buf)?;
+        count += 1;
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+/// convert length-delimited protobuf messages to NDJSON records one at a time
+/// (returns the number of records)
+///
+/// records are rendered as `ProtobufDescriptor::message_to_json_with_options`
+/// except `options.pretty` (ignored: a record is always a single line)
+pub fn length_delimited_to_ndjson<R: Read, W: Write>(
+    descriptor: &MessageDescriptor,
+    mut reader: R,
+    writer: &mut W,
+    options: &JsonPrintOptions,
+) -> Result<usize> {
+    let serialize_options = options.serialize_options();
+    let mut count = 0;
+    let mut buf = Vec::new();
+    while let Some(len) = read_length(&mut reader)? {
+        if len > MAX_RECORD_SIZE {
+            return Err(anyhow!(
+                "record {} is too large: {} bytes (max: {})",
+                count + 1,
+                len,
+                MAX_RECORD_SIZE
+            ));
+        }
+        buf.resize(len, 0);
+        reader
+            .read_exact(&mut buf)
+            .context(format!("on reading record {}", count + 1))?;
+        let message =
+            DynamicMessage::decode(descriptor.clone(), buf.as_slice()).context(format!(
+                "on decoding record {} as {}",
+                count + 1,
+                descriptor.full_name()
+            ))?;
+        if options.rewrites_bytes() {
+            let value = ProtobufDescriptor::message_to_json_value(&message, options)?;
+            serde_json::to_writer(&mut *writer, &value)?;
+        } else {
+            let mut serializer = serde_json::Serializer::new(&mut *writer);
+            message.serialize_with_options(&mut serializer, &serialize_options)?;
+        }
+        writer.write_all(b"\n")?;
+        count += 1;
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+// read varint length prefix (None at the end of input)
+fn read_length<R: Read>(reader: &mut R) -> Result<Option<usize>> {
+    let mut value: u64 = 0;
+    for i in 0..10 {
+        let mut byte = [0u8; 1];
+        match reader.read_exact(&mut byte) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof && i == 0 => return Ok(None),
+            Err(e) => return Err(e).context("on reading length prefix"),
+        }
+        value |= ((byte[0] & 0x7f) as u64) << (i * 7);
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value as usize));
+        }
+    }
+    Err(anyhow!("invalid length prefix (varint too long)"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::printer::BytesFormat;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_ndjson_length_delimited_roundtrip() -> Result<()> {
+        let proto_string = r#"
+        syntax = "proto3";
+        package jobworkerp.data;
+        message Job {
+            int64 id = 1;
+            string name = 2;
+        }
+        "#;
+        let descriptor = ProtobufDescriptor::new(&proto_string.to_string())?;
+        let job = descriptor
+            .get_message_by_name("jobworkerp.data.Job")
+            .unwrap();
+        let ndjson = "{\"id\":\"1\",\"name\":\"a\"}\n\n{\"id\":\"2\"}\n{}\n";
+
+        let mut encoded = Vec::new();
+        assert_eq!(
+            ndjson_to_length_delimited(&job, Cursor::new(ndjson), &mut encoded)?,
+            3
+        );
+        let mut decoded = Vec::new();
+        let count = length_delimited_to_ndjson(
+            &job,
+            Cursor::new(&encoded),
+            &mut decoded,
+            &JsonPrintOptions::default(),
+        )?;
+        assert_eq!(count, 3);
+        assert_eq!(
+            String::from_utf8(decoded)?,
+            "{\"id\":\"1\",\"name\":\"a\"}\n{\"id\":\"2\"}\n{}\n"
+        );
+
+        let err = ndjson_to_length_delimited(&job, Cursor::new("{}\n{\"x\":1}\n"), &mut Vec::new())
+            .unwrap_err();
+        assert!(format!("{:?}", err).contains("line 2"));
+        // truncated record
+        assert!(length_delimited_to_ndjson(
+            &job,
+            Cursor::new(&encoded[..3]),
+            &mut Vec::new(),
+            &JsonPrintOptions::default()
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_length_delimited_to_ndjson_options() -> Result<()> {
+        let proto_string = r#"
+        syntax = "proto3";
+        message Chunk {
+            bytes data = 1;
+        }
+        "#;
+        let descriptor = ProtobufDescriptor::new(&proto_string.to_string())?;
+        let chunk = descriptor.get_message_by_name("Chunk").unwrap();
+        let mut encoded = Vec::new();
+        ndjson_to_length_delimited(
+            &chunk,
+            Cursor::new("{\"data\":\"aGVsbG8=\"}\n"),
+            &mut encoded,
+        )?;
+        let options = JsonPrintOptions {
+            bytes_format: Some(BytesFormat::Utf8Lossy),
+            max_bytes_preview: Some(2),
+            pretty: true,
+            ..Default::default()
+        };
+        let mut decoded = Vec::new();
+        length_delimited_to_ndjson(&chunk, Cursor::new(&encoded), &mut decoded, &options)?;
+        assert_eq!(
+            String::from_utf8(decoded)?,
+            "{\"data\":\"he… (5 bytes)\"}\n"
+        );
+        Ok(())
+    }
+}
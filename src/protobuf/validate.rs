@@ -0,0 +1,415 @@
+use super::printer::kind_name;
+use prost_reflect::{Cardinality, FieldDescriptor, Kind, MessageDescriptor};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// validation error of a json field (path is a JSON pointer, ex. "/worker/counts/a")
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    pub path: String,
+    pub kind: FieldErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldErrorKind {
+    /// json is not parsable or message is not found
+    InvalidInput(String),
+    UnknownField,
+    MissingRequired,
+    /// json type is not acceptable for the field type
+    TypeMismatch {
+        expected: String,
+        actual: String,
+    },
+    /// json type is acceptable but the value is not (ex. out of range, unknown enum name)
+    InvalidValue {
+        expected: String,
+        value: String,
+    },
+    /// multiple fields of a oneof are set
+    MultipleOneofFields {
+        oneof: String,
+        fields: Vec<String>,
+    },
+}
+
+impl FieldError {
+    pub fn new(path: impl Into<String>, kind: FieldErrorKind) -> Self {
+        Self {
+            path: path.into(),
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "/"
+        } else {
+            &self.path
+        };
+        write!(f, "{}: {}", path, self.kind)
+    }
+}
+
+impl fmt::Display for FieldErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldErrorKind::InvalidInput(message) => write!(f, "invalid input: {}", message),
+            FieldErrorKind::UnknownField => write!(f, "unknown field"),
+            FieldErrorKind::MissingRequired => write!(f, "missing required field"),
+            FieldErrorKind::TypeMismatch { expected, actual } => {
+                write!(f, "type mismatch: expected {}, actual {}", expected, actual)
+            }
+            FieldErrorKind::InvalidValue { expected, value } => {
+                write!(f, "invalid value for {}: {}", expected, value)
+            }
+            FieldErrorKind::MultipleOneofFields { oneof, fields } => {
+                write!(
+                    f,
+                    "multiple fields of oneof {} are set: {}",
+                    oneof,
+                    fields.join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// validate json against the message descriptor and report all errors
+/// (unknown fields, type mismatches, missing required fields (proto2), multiple oneof fields)
+pub fn validate_json(descriptor: &MessageDescriptor, json: &str) -> Result<(), Vec<FieldError>> {
+    let value: Value = serde_json::from_str(json).map_err(|e| {
+        vec![FieldError::new(
+            "",
+            FieldErrorKind::InvalidInput(e.to_string()),
+        )]
+    })?;
+    let mut errors = Vec::new();
+    validate_message(descriptor, &value, "", &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn validate_message(
+    descriptor: &MessageDescriptor,
+    value: &Value,
+    path: &str,
+    errors: &mut Vec<FieldError>,
+) {
+    if validate_well_known_type(descriptor, value, path, errors) {
+        return;
+    }
+    let Value::Object(object) = value else {
+        errors.push(type_mismatch(path, descriptor.full_name(), value));
+        return;
+    };
+    let mut oneof_fields: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (key, v) in object {
+        let field_path = format!("{}/{}", path, escape_pointer(key));
+        let Some(field) = descriptor
+            .get_field_by_json_name(key)
+            .or_else(|| descriptor.get_field_by_name(key))
+        else {
+            errors.push(FieldError::new(field_path, FieldErrorKind::UnknownField));
+            continue;
+        };
+        if v.is_null() {
+            continue;
+        }
+        if let Some(oneof) = field.containing_oneof().filter(|o| !o.is_synthetic()) {
+            oneof_fields
+                .entry(oneof.name().to_string())
+                .or_default()
+                .push(field.name().to_string());
+        }
+        validate_field(&field, v, &field_path, errors);
+    }
+    for (oneof, mut fields) in oneof_fields {
+        if fields.len() > 1 {
+            fields.sort();
+            errors.push(FieldError::new(
+                path,
+                FieldErrorKind::MultipleOneofFields { oneof, fields },
+            ));
+        }
+    }
+    for field in descriptor.fields() {
+        if field.cardinality() == Cardinality::Required
+            && !object.contains_key(field.json_name())
+            && !object.contains_key(field.name())
+        {
+            errors.push(FieldError::new(
+                format!("{}/{}", path, escape_pointer(field.json_name())),
+                FieldErrorKind::MissingRequired,
+            ));
+        }
+    }
+}
+
+fn validate_field(
+    field: &FieldDescriptor,
+    value: &Value,
+    path: &str,
+    errors: &mut Vec<FieldError>,
+) {
+    if field.is_map() {
+        let (Kind::Message(entry), Value::Object(object)) = (field.kind(), value) else {
+            errors.push(type_mismatch(path, "map", value));
+            return;
+        };
+        let key_kind = entry.map_entry_key_field().kind();
+        let value_kind = entry.map_entry_value_field().kind();
+        for (key, v) in object {
+            let entry_path = format!("{}/{}", path, escape_pointer(key));
+            if !is_valid_map_key(&key_kind, key) {
+                errors.push(FieldError::new(
+                    entry_path.clone(),
+                    FieldErrorKind::InvalidValue {
+                        expected: kind_name(&key_kind),
+                        value: key.clone(),
+                    },
+                ));
+            }
+            validate_value(&value_kind, v, &entry_path, errors);
+        }
+    } else if field.is_list() {
+        let Value::Array(array) = value else {
+            errors.push(type_mismatch(path, "array", value));
+            return;
+        };
+        for (i, v) in array.iter().enumerate() {
+            validate_value(&field.kind(), v, &format!("{}/{}", path, i), errors);
+        }
+    } else {
+        validate_value(&field.kind(), value, path, errors);
+    }
+}
+
+fn validate_value(kind: &Kind, value: &Value, path: &str, errors: &mut Vec<FieldError>) {
+    let invalid_value = || {
+        FieldError::new(
+            path,
+            FieldErrorKind::InvalidValue {
+                expected: kind_name(kind),
+                value: value.to_string(),
+            },
+        )
+    };
+    match kind {
+        Kind::Message(m) => validate_message(m, value, path, errors),
+        Kind::Enum(e) => match value {
+            Value::String(s) if e.get_value_by_name(s).is_none() => errors.push(invalid_value()),
+            Value::Number(_)
+                if integer_in_range(value, i32::MIN as i128, i32::MAX as i128) != Some(true) =>
+            {
+                errors.push(invalid_value())
+            }
+            Value::String(_) | Value::Number(_) => {}
+            _ => errors.push(type_mismatch(path, &kind_name(kind), value)),
+        },
+        Kind::Bool => {
+            if !value.is_boolean() {
+                errors.push(type_mismatch(path, "bool", value))
+            }
+        }
+        Kind::String | Kind::Bytes => {
+            if !value.is_string() {
+                errors.push(type_mismatch(path, &kind_name(kind), value))
+            }
+        }
+        Kind::Double | Kind::Float => match value {
+            Value::Number(_) => {}
+            Value::String(s)
+                if matches!(s.as_str(), "NaN" | "Infinity" | "-Infinity")
+                    || s.parse::<f64>().is_ok() => {}
+            Value::String(_) => errors.push(invalid_value()),
+            _ => errors.push(type_mismatch(path, &kind_name(kind), value)),
+        },
+        _ => {
+            let (min, max) = integer_range(kind);
+            match integer_in_range(value, min, max) {
+                Some(true) => {}
+                Some(false) => errors.push(invalid_value()),
+                None => errors.push(type_mismatch(path, &kind_name(kind), value)),
+            }
+        }
+    }
+}
+
+// google.protobuf.* types with special json representation (returns true if handled)
+fn validate_well_known_type(
+    descriptor: &MessageDescriptor,
+    value: &Value,
+    path: &str,
+    errors: &mut Vec<FieldError>,
+) -> bool {
+    let expected = match descriptor.full_name() {
+        "google.protobuf.Timestamp" | "google.protobuf.Duration" | "google.protobuf.FieldMask" => {
+            "string"
+        }
+        "google.protobuf.Struct" | "google.protobuf.Any" | "google.protobuf.Empty" => "object",
+        "google.protobuf.ListValue" => "array",
+        "google.protobuf.Value" => return true,
+        "google.protobuf.DoubleValue"
+        | "google.protobuf.FloatValue"
+        | "google.protobuf.Int64Value"
+        | "google.protobuf.UInt64Value"
+        | "google.protobuf.Int32Value"
+        | "google.protobuf.UInt32Value"
+        | "google.protobuf.BoolValue"
+        | "google.protobuf.StringValue"
+        | "google.protobuf.BytesValue" => {
+            if let Some(field) = descriptor.get_field_by_name("value") {
+                validate_value(&field.kind(), value, path, errors);
+            }
+            return true;
+        }
+        _ => return false,
+    };
+    if json_type_name(value) != expected {
+        errors.push(type_mismatch(path, descriptor.full_name(), value));
+    }
+    true
+}
+
+fn integer_range(kind: &Kind) -> (i128, i128) {
+    match kind {
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => (i32::MIN as i128, i32::MAX as i128),
+        Kind::Uint32 | Kind::Fixed32 => (0, u32::MAX as i128),
+        Kind::Uint64 | Kind::Fixed64 => (0, u64::MAX as i128),
+        _ => (i64::MIN as i128, i64::MAX as i128),
+    }
+}
+
+// integers are accepted as json numbers or strings (None: not an integer type)
+fn integer_in_range(value: &Value, min: i128, max: i128) -> Option<bool> {
+    let n = match value {
+        Value::Number(n) => n
+            .as_i64()
+            .map(|n| n as i128)
+            .or_else(|| n.as_u64().map(|n| n as i128))
+            .or_else(|| {
+                n.as_f64()
+                    .filter(|f| f.fract() == 0.0 && f.abs() < 2f64.powi(64))
+                    .map(|f| f as i128)
+            }),
+        Value::String(s) => s.parse::<i128>().ok(),
+        _ => return None,
+    };
+    Some(n.is_some_and(|n| (min..=max).contains(&n)))
+}
+
+fn is_valid_map_key(kind: &Kind, key: &str) -> bool {
+    match kind {
+        Kind::String => true,
+        Kind::Bool => key == "true" || key == "false",
+        _ => {
+            let (min, max) = integer_range(kind);
+            key.parse::<i128>().is_ok_and(|n| (min..=max).contains(&n))
+        }
+    }
+}
+
+fn type_mismatch(path: &str, expected: &str, actual: &Value) -> FieldError {
+    FieldError::new(
+        path,
+        FieldErrorKind::TypeMismatch {
+            expected: expected.to_string(),
+            actual: json_type_name(actual).to_string(),
+        },
+    )
+}
+
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+// escape a JSON pointer token (RFC 6901)
+fn escape_pointer(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::ProtobufDescriptor;
+    use anyhow::Result;
+
+    #[test]
+    fn test_validate_json() -> Result<()> {
+        let proto_string = r#"
+        syntax = "proto2";
+
+        package jobworkerp.data;
+
+        enum Status {
+            UNKNOWN = 0;
+            RUNNING = 1;
+        }
+        message Worker {
+            required string name = 1;
+            map<string, int32> counts = 2;
+        }
+        message Job {
+            required int64 id = 1;
+            repeated string tags = 2;
+            optional Worker worker = 3;
+            optional Status status = 4;
+            optional uint32 retry_count = 5;
+            oneof target {
+                string url = 6;
+                string path = 7;
+            }
+        }
+        "#;
+        let descriptor = ProtobufDescriptor::new(&proto_string.to_string())?;
+        let json = r#"{"id": "1", "tags": ["a"], "worker": {"name": "w", "counts": {"a": 1}},
+            "status": "RUNNING", "retryCount": 3, "url": "x"}"#;
+        assert_eq!(
+            descriptor.validate_json("jobworkerp.data.Job", json),
+            Ok(())
+        );
+
+        let json = r#"{"tags": ["a", 1], "worker": {"counts": {"a": "x"}}, "status": "STOPPED",
+            "retry_count": -1, "url": "x", "path": "y", "extra": true}"#;
+        let mut errors = descriptor
+            .validate_json("jobworkerp.data.Job", json)
+            .unwrap_err();
+        errors.sort_by(|a, b| a.path.cmp(&b.path));
+        let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "/: multiple fields of oneof target are set: path, url",
+                "/extra: unknown field",
+                "/id: missing required field",
+                "/retry_count: invalid value for uint32: -1",
+                "/status: invalid value for jobworkerp.data.Status: \"STOPPED\"",
+                "/tags/1: type mismatch: expected string, actual number",
+                "/worker/counts/a: invalid value for int32: \"x\"",
+                "/worker/name: missing required field",
+            ]
+        );
+
+        assert!(descriptor
+            .validate_json("jobworkerp.data.Job", "{")
+            .is_err());
+        assert!(descriptor
+            .validate_json("jobworkerp.data.None", "{}")
+            .is_err());
+        Ok(())
+    }
+}
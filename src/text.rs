@@ -1,7 +1,22 @@
+pub mod chunking;
+pub mod normalize;
+pub mod similarity;
+
 use anyhow::{Context, Result};
+use rand::{rngs::OsRng, Rng};
 use regex::Regex;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::BufRead;
+use std::ops::Range;
+
+/// tokenizer used for limits expressed in model tokens (ex. `util::text::split_text_by_tokens`)
+pub trait TokenProvider {
+    fn tokenize(&self, text: &str) -> Result<Vec<u32>>;
+    fn estimate_token_count(&self, text: &str) -> Result<usize> {
+        self.tokenize(text).map(|tokens| tokens.len())
+    }
+}
 
 // for deserialize from env
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
@@ -10,6 +25,12 @@ pub struct SentenceSplitterCreator {
     pub delimiter_chars: Option<String>,
     pub force: Option<String>,
     pub parenthese_pairs: Option<String>,
+    // comma separated (ex. "Dr.,e.g.")
+    pub abbreviations: Option<String>,
+    // 複数文字の区切り (ex. "[!?！？]+|\.{3}|…+")。マッチ全体の後で区切る
+    pub delimiter_regex: Option<String>,
+    // 括弧内で区切らない最大文字数。超えた場合は閉じ括弧がないものとして括弧の外側として区切り直す
+    pub max_parenthese_length: Option<usize>,
 }
 impl SentenceSplitterCreator {
     // max input length for bert (max_position_embeddings)
@@ -25,6 +46,14 @@ impl SentenceSplitterCreator {
     // があるので両方マッチしていいものは明示的に列挙する
     pub const DELIMITER_CHARS: [char; 7] = ['。', '．', '！', '？', '!', '?', '\n'];
 
+    // 英文の略語。"."を区切り文字に含めた場合にこれらの末尾の"."では区切らない
+    pub const ABBREVIATIONS: [&'static str; 38] = [
+        "Mr.", "Mrs.", "Ms.", "Dr.", "Prof.", "Sr.", "Jr.", "St.", "Mt.", "vs.", "etc.", "e.g.",
+        "i.e.", "cf.", "al.", "approx.", "No.", "Vol.", "pp.", "Fig.", "Inc.", "Ltd.", "Co.",
+        "Corp.", "U.S.", "U.K.", "a.m.", "p.m.", "Jan.", "Feb.", "Mar.", "Apr.", "Aug.", "Sep.",
+        "Sept.", "Oct.", "Nov.", "Dec.",
+    ];
+
     pub fn new(
         max_buf_length: Option<usize>,
         delimiter_chars: Option<String>,
@@ -36,6 +65,9 @@ impl SentenceSplitterCreator {
             delimiter_chars,
             force,
             parenthese_pairs,
+            abbreviations: None,
+            delimiter_regex: None,
+            max_parenthese_length: None,
         }
     }
     pub fn new_by_env() -> Result<Self> {
@@ -69,6 +101,22 @@ impl SentenceSplitterCreator {
                 pairs.into_iter().collect()
             })
             .unwrap_or(Self::PARENTHESE_PAIRS.iter().cloned().collect());
+        let abbreviations = self
+            .abbreviations
+            .as_ref()
+            .map(|s| {
+                s.split(',')
+                    .map(|a| a.trim().to_string())
+                    .filter(|a| !a.is_empty())
+                    .collect()
+            })
+            .unwrap_or_else(default_abbreviations);
+        let delimiter_regex = self
+            .delimiter_regex
+            .as_ref()
+            .map(|p| Regex::new(p).map(DelimiterRegex))
+            .transpose()
+            .context("invalid delimiter_regex:")?;
         let rev_parentheses = parenthese_pairs
             .iter()
             .map(|(a, b)| (*b, *a)) // iterate reverse
@@ -80,6 +128,9 @@ impl SentenceSplitterCreator {
             force,
             parenthese_pairs,
             rev_parentheses,
+            abbreviations,
+            delimiter_regex,
+            max_parenthese_length: self.max_parenthese_length,
         })
     }
 }
@@ -91,6 +142,50 @@ pub struct SentenceSplitter {
     force: HashSet<char>,
     parenthese_pairs: HashMap<char, char>,
     rev_parentheses: HashMap<char, char>,
+    // 未指定の場合 (abbreviations追加前にserializeしたもの) はSentenceSplitterCreatorと同じ既定の略語
+    #[serde(default = "default_abbreviations")]
+    abbreviations: HashSet<String>,
+    delimiter_regex: Option<DelimiterRegex>,
+    max_parenthese_length: Option<usize>,
+}
+
+fn default_abbreviations() -> HashSet<String> {
+    SentenceSplitterCreator::ABBREVIATIONS
+        .iter()
+        .map(|a| a.to_string())
+        .collect()
+}
+
+// compiled delimiter pattern (serialize as pattern string)
+#[derive(Debug, Clone)]
+pub struct DelimiterRegex(Regex);
+
+impl PartialEq for DelimiterRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+impl Serialize for DelimiterRegex {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+impl<'de> Deserialize<'de> for DelimiterRegex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&pattern)
+            .map(DelimiterRegex)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+// 区切りの正規表現にマッチした範囲に対する文字の位置
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DelimiterMatch {
+    Outside,
+    Inside,
+    // マッチの最後の文字
+    End,
 }
 
 impl SentenceSplitter {
@@ -100,61 +195,207 @@ impl SentenceSplitter {
     }
 
     pub fn split(&self, text: String) -> Vec<String> {
-        let mut sentences: Vec<String> = vec![];
-        let mut buf: Vec<char> = Vec::with_capacity(self.max_buf_length);
-        let mut waiting_stack: Vec<&char> = vec![];
+        self.split_with_spans(&text)
+            .into_iter()
+            .map(|(_, sentence)| sentence.to_string())
+            .collect()
+    }
 
-        for c in text.chars() {
-            buf.push(c);
+    /// split text same as `split`, with byte ranges of sentences in the text
+    pub fn split_with_spans<'a>(&self, text: &'a str) -> Vec<(Range<usize>, &'a str)> {
+        let matches = self.delimiter_matches(text);
+        let mut state = SplitState::default();
+        let mut spans = vec![];
+        self.scan(text, &matches, &mut state, text.len(), true, &mut spans);
+        if state.start < text.len() {
+            spans.push(state.start..text.len());
+        }
+        spans
+            .into_iter()
+            .map(|range| (range.clone(), &text[range]))
+            .collect()
+    }
 
-            if let Some(t) = self.parenthese_pairs.get(&c) {
-                waiting_stack.push(t);
-            } else if let Some(d) = waiting_stack.last() {
-                if c == **d {
-                    waiting_stack.pop();
-                } else if self.force.contains(&c) {
-                    sentences.push(buf.into_iter().collect());
-                    buf = Vec::with_capacity(self.max_buf_length);
-                    waiting_stack.clear();
+    // stateの位置からuntil (バイト位置) の手前まで区切り、区切った文の範囲をspansに追加する
+    // (stateを残しておけばtextに続きを追加して再開できる)
+    // recover_unclosed: 閉じ括弧がないまま終わった場合、最も外側の開き括弧を通常の文字として区切り直す
+    // (続きのテキストで閉じられる可能性がある場合はfalse)
+    fn scan(
+        &self,
+        text: &str,
+        matches: &[Range<usize>],
+        state: &mut SplitState,
+        until: usize,
+        recover_unclosed: bool,
+        spans: &mut Vec<Range<usize>>,
+    ) {
+        loop {
+            if let Some(&(_, open_pos, open_len)) = state.waiting_stack.first() {
+                let too_long = self
+                    .max_parenthese_length
+                    .is_some_and(|max| state.len - open_len > max);
+                if too_long || (recover_unclosed && state.i >= text.len()) {
+                    // restart from the next of the open char
+                    state.waiting_stack.clear();
+                    state.i = open_pos + text[open_pos..].chars().next().map_or(1, char::len_utf8);
+                    state.len = open_len + 1;
                 }
-            } else if self.delemeters.contains(&c) {
-                sentences.push(buf.into_iter().collect());
-                buf = Vec::with_capacity(self.max_buf_length);
             }
+            if state.i >= until {
+                break;
+            }
+            let Some(c) = text[state.i..].chars().next() else {
+                break;
+            };
+            let i = state.i;
+            let end = i + c.len_utf8();
+            let delimiter_match = Self::delimiter_match_at(matches, i..end);
+            state.len += 1;
 
-            if buf.len() >= self.max_buf_length {
-                sentences.push(buf.into_iter().collect());
-                buf = Vec::with_capacity(self.max_buf_length);
-                waiting_stack.clear()
+            let mut divide = false;
+            // 閉じ括弧を先に見る (同じ文字の括弧 (ex. '"') と閉じられていない内側の括弧のため)
+            if let Some(pos) = state.waiting_stack.iter().rposition(|(d, _, _)| *d == c) {
+                state.waiting_stack.truncate(pos);
+            } else if let Some(t) = self.parenthese_pairs.get(&c) {
+                state.waiting_stack.push((*t, i, state.len - 1));
+            } else if !state.waiting_stack.is_empty() {
+                if self.force.contains(&c) {
+                    divide = true;
+                    state.waiting_stack.clear();
+                }
+            } else if delimiter_match == DelimiterMatch::End
+                || (delimiter_match == DelimiterMatch::Outside
+                    && self.delemeters.contains(&c)
+                    && !self.is_inner_period(&text[state.start..end], text[end..].chars().next()))
+            {
+                divide = true;
             }
+
+            if state.len >= self.max_buf_length {
+                divide = true;
+                state.waiting_stack.clear()
+            }
+            if divide {
+                spans.push(state.start..end);
+                state.start = end;
+                state.len = 0;
+            }
+            state.i = end;
         }
-        if !buf.is_empty() {
-            sentences.push(buf.into_iter().collect());
+    }
+
+    /// split sentences read from reader incrementally
+    ///
+    /// only the last incomplete sentence is buffered and scanning resumes where the previous line stopped
+    /// (`max_buf_length` and `max_parenthese_length` apply to the buffered sentence as in `split`).
+    /// the result is same as `split` for the whole text, except that `delimiter_regex` is matched
+    /// within each line.
+    /// a read error is logged (warn) and ends the iteration after the buffered text
+    pub fn split_iter<'a, R: BufRead + 'a>(
+        &'a self,
+        reader: R,
+    ) -> impl Iterator<Item = String> + 'a {
+        SplitIter {
+            splitter: self,
+            reader,
+            buf: String::new(),
+            matches: Vec::new(),
+            state: SplitState::default(),
+            ready: VecDeque::new(),
+            eof: false,
+        }
+    }
+
+    fn delimiter_matches(&self, text: &str) -> Vec<Range<usize>> {
+        self.delimiter_regex
+            .as_ref()
+            .map(|r| {
+                r.0.find_iter(text)
+                    .filter(|m| !m.as_str().is_empty())
+                    .map(|m| m.range())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // matches: 区切りにマッチした範囲 (昇順), char_range: 文字のバイト範囲
+    fn delimiter_match_at(matches: &[Range<usize>], char_range: Range<usize>) -> DelimiterMatch {
+        let i = matches.partition_point(|m| m.end <= char_range.start);
+        match matches.get(i) {
+            Some(m) if m.start <= char_range.start && m.end == char_range.end => {
+                DelimiterMatch::End
+            }
+            Some(m) if m.start <= char_range.start => DelimiterMatch::Inside,
+            _ => DelimiterMatch::Outside,
+        }
+    }
+
+    // 文末ではない"."か (小数点、略語、略語の途中 (ex. "U.S."の"U."))
+    // sentence: "."までの文字列, next: "."の次の文字
+    fn is_inner_period(&self, sentence: &str, next: Option<char>) -> bool {
+        let mut rev = sentence.chars().rev();
+        if rev.next() != Some('.') {
+            return false;
+        }
+        // decimal number (ex. 3.14)
+        if rev.next().is_some_and(|p| p.is_ascii_digit())
+            && next.is_some_and(|n| n.is_ascii_digit())
+        {
+            return true;
         }
-        sentences
+        let token = sentence
+            .rsplit(char::is_whitespace)
+            .next()
+            .unwrap_or_default();
+        let token = token.trim_start_matches(|c: char| !c.is_alphanumeric());
+        if token.is_empty() {
+            return false;
+        }
+        // allow capitalized at the beginning of sentence (ex. "E.g.")
+        let mut uncapitalized = token.chars();
+        let uncapitalized: String = uncapitalized
+            .next()
+            .map(|f| f.to_lowercase().chain(uncapitalized).collect())
+            .unwrap_or_default();
+        if self.abbreviations.contains(token) || self.abbreviations.contains(&uncapitalized) {
+            return true;
+        }
+        next.is_some_and(|n| !n.is_whitespace())
+            && self.abbreviations.iter().any(|a| {
+                a.len() > token.len()
+                    && (a.starts_with(token) || a.starts_with(uncapitalized.as_str()))
+            })
     }
 
     //
     // XXX 最初の文がmaxより長い場合逆に切りつめられる。。。
     // (!!などの連続は扱いやすそうなのでどうにかならないか考える)
+    // 略語は`split`と同じく扱うが、max_parenthese_lengthと閉じられていない括弧の区切り直しはしない
     pub fn split_r(&self, text: String) -> Vec<String> {
         let mut sentences: VecDeque<String> = VecDeque::new();
         let mut buf: VecDeque<char> = VecDeque::with_capacity(self.max_buf_length);
         let mut waiting_stack: Vec<&char> = vec![];
+        let matches = self.delimiter_matches(&text);
 
         // iterate reverse
-        for c in text.chars().rev() {
-            if let Some(t) = self.rev_parentheses.get(&c) {
+        for (start, c) in text.char_indices().rev() {
+            let delimiter_match = Self::delimiter_match_at(&matches, start..start + c.len_utf8());
+            if let Some(pos) = waiting_stack.iter().rposition(|d| **d == c) {
+                waiting_stack.truncate(pos);
+            } else if let Some(t) = self.rev_parentheses.get(&c) {
                 waiting_stack.push(t);
-            } else if let Some(d) = waiting_stack.last() {
-                if c == **d {
-                    waiting_stack.pop();
-                } else if self.force.contains(&c) {
+            } else if !waiting_stack.is_empty() {
+                if self.force.contains(&c) {
                     sentences.push_front(buf.into_iter().collect());
                     buf = VecDeque::with_capacity(self.max_buf_length);
                     waiting_stack.clear();
                 }
-            } else if self.delemeters.contains(&c) && !buf.is_empty() {
+            } else if (delimiter_match == DelimiterMatch::End
+                || (delimiter_match == DelimiterMatch::Outside
+                    && self.delemeters.contains(&c)
+                    && !self.is_inner_period(&text[..start + c.len_utf8()], buf.front().copied())))
+                && !buf.is_empty()
+            {
                 sentences.push_front(buf.into_iter().collect());
                 buf = VecDeque::with_capacity(self.max_buf_length);
             }
@@ -191,9 +432,129 @@ impl SentenceSplitter {
         divided
     }
 }
+// scanning position of SentenceSplitter::scan (byte positions in the text)
+#[derive(Debug, Default)]
+struct SplitState {
+    // start byte and char count of the current sentence
+    start: usize,
+    len: usize,
+    // (close char, byte position of open char, char count of sentence before open char)
+    waiting_stack: Vec<(char, usize, usize)>,
+    // next byte to scan
+    i: usize,
+}
+
+struct SplitIter<'a, R> {
+    splitter: &'a SentenceSplitter,
+    reader: R,
+    // read text (before state.start is already emitted)
+    buf: String,
+    // delimiter_regex matches in buf
+    matches: Vec<Range<usize>>,
+    state: SplitState,
+    ready: VecDeque<String>,
+    eof: bool,
+}
+
+impl<R> SplitIter<'_, R> {
+    fn scan(&mut self, until: usize, recover_unclosed: bool) {
+        let mut spans = vec![];
+        self.splitter.scan(
+            &self.buf,
+            &self.matches,
+            &mut self.state,
+            until,
+            recover_unclosed,
+            &mut spans,
+        );
+        self.ready
+            .extend(spans.into_iter().map(|range| self.buf[range].to_string()));
+        // drop emitted text when it is the larger part (amortized linear)
+        let start = self.state.start;
+        if start > 0 && start * 2 >= self.buf.len() {
+            self.buf.drain(..start);
+            self.matches.retain(|m| m.end > start);
+            for m in self.matches.iter_mut() {
+                *m = m.start.saturating_sub(start)..m.end - start;
+            }
+            for (_, open_pos, _) in self.state.waiting_stack.iter_mut() {
+                *open_pos -= start;
+            }
+            self.state.i -= start;
+            self.state.start = 0;
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for SplitIter<'_, R> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            if let Some(sentence) = self.ready.pop_front() {
+                return Some(sentence);
+            }
+            if self.eof {
+                return None;
+            }
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(n) if n > 0 => {
+                    let offset = self.buf.len();
+                    self.matches.extend(
+                        self.splitter
+                            .delimiter_matches(&line)
+                            .into_iter()
+                            .map(|m| m.start + offset..m.end + offset),
+                    );
+                    self.buf.push_str(&line);
+                    // a period at the end is divided or not by the next char
+                    let until = self.buf.len() - usize::from(self.buf.ends_with('.'));
+                    // unclosed parentheses may be closed in the next lines
+                    self.scan(until, false);
+                }
+                r => {
+                    if let Err(e) = r {
+                        tracing::warn!("stop splitting by read error: {:?}", e);
+                    }
+                    self.eof = true;
+                    self.scan(self.buf.len(), true);
+                    if self.state.start < self.buf.len() {
+                        self.ready
+                            .push_back(self.buf[self.state.start..].to_string());
+                    }
+                    self.buf.clear();
+                }
+            }
+        }
+    }
+}
+
+/// alphabet of keys generated by TextUtil::generate_secure_key_with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAlphabet {
+    /// [0-9A-Za-z]
+    Base62,
+    /// crockford base32 (no I, L, O, U: case insensitive and unambiguous)
+    Base32,
+}
+
+impl KeyAlphabet {
+    pub fn chars(&self) -> &'static [u8] {
+        match self {
+            KeyAlphabet::Base62 => {
+                b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz"
+            }
+            KeyAlphabet::Base32 => b"0123456789ABCDEFGHJKMNPQRSTVWXYZ",
+        }
+    }
+}
+
 pub struct TextUtil {}
 
 impl TextUtil {
+    /// capitalize the first char of each '_' separated part and keep the rest as is
+    /// (ex. "my_HTTP_server" -> "MyHTTPServer". use `to_pascal` to normalize case by words)
     pub fn snake_to_camel(s: &str) -> String {
         s.split('_')
             .map(|w| {
@@ -205,6 +566,108 @@ impl TextUtil {
             })
             .collect()
     }
+
+    /// split identifier into words by '_', '-', whitespace and case boundaries
+    ///
+    /// acronyms are kept as a word (ex. "HTTPServerV2" -> ["HTTP", "Server", "V2"])
+    pub fn split_words(s: &str) -> Vec<String> {
+        let chars: Vec<char> = s.chars().collect();
+        let mut words = Vec::new();
+        let mut current = String::new();
+        for (i, &c) in chars.iter().enumerate() {
+            if c == '_' || c == '-' || c.is_whitespace() {
+                if !current.is_empty() {
+                    words.push(std::mem::take(&mut current));
+                }
+                continue;
+            }
+            if c.is_uppercase() && !current.is_empty() {
+                let prev = chars[i - 1];
+                let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+                if (prev.is_alphanumeric() && !prev.is_uppercase())
+                    || (prev.is_uppercase() && next_lower)
+                {
+                    words.push(std::mem::take(&mut current));
+                }
+            }
+            current.push(c);
+        }
+        if !current.is_empty() {
+            words.push(current);
+        }
+        words
+    }
+
+    /// to snake_case (ex. "HTTPServer" -> "http_server")
+    pub fn camel_to_snake(s: &str) -> String {
+        Self::join_words(s, "_", str::to_lowercase)
+    }
+
+    /// to kebab-case (ex. "HTTPServer" -> "http-server")
+    pub fn to_kebab(s: &str) -> String {
+        Self::join_words(s, "-", str::to_lowercase)
+    }
+
+    /// to PascalCase (ex. "HTTP_SERVER" -> "HttpServer")
+    pub fn to_pascal(s: &str) -> String {
+        Self::join_words(s, "", Self::capitalize)
+    }
+
+    /// to SCREAMING_SNAKE_CASE (ex. "httpServer" -> "HTTP_SERVER")
+    pub fn to_screaming_snake(s: &str) -> String {
+        Self::join_words(s, "_", str::to_uppercase)
+    }
+
+    /// random base62 key from the os random source (len 22 ~ 131bit)
+    pub fn generate_secure_key(len: usize) -> String {
+        Self::generate_secure_key_with(len, KeyAlphabet::Base62)
+    }
+
+    /// random key from the os random source (chars are chosen uniformly)
+    pub fn generate_secure_key_with(len: usize, alphabet: KeyAlphabet) -> String {
+        let chars = alphabet.chars();
+        (0..len)
+            .map(|_| chars[OsRng.gen_range(0..chars.len())] as char)
+            .collect()
+    }
+
+    /// ULID (48bit unix time millis + 80bit random in crockford base32, 26 chars)
+    ///
+    /// lexicographically sortable by generation time (in millis)
+    pub fn generate_ulid() -> String {
+        let millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        Self::ulid_from_parts(millis, OsRng.gen::<u128>())
+    }
+
+    fn ulid_from_parts(millis: u64, random: u128) -> String {
+        let value = ((millis as u128 & 0xFFFF_FFFF_FFFF) << 80) | (random >> 48);
+        let chars = KeyAlphabet::Base32.chars();
+        (0..26)
+            .map(|i| chars[((value >> (125 - i * 5)) & 0x1F) as usize] as char)
+            .collect()
+    }
+
+    fn join_words(s: &str, separator: &str, f: impl Fn(&str) -> String) -> String {
+        Self::split_words(s)
+            .iter()
+            .map(|w| f(w))
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+
+    fn capitalize(word: &str) -> String {
+        let mut c = word.chars();
+        match c.next() {
+            None => String::new(),
+            Some(f) => f
+                .to_uppercase()
+                .chain(c.as_str().to_lowercase().chars())
+                .collect(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -219,6 +682,9 @@ mod tests {
             delimiter_chars: Some("。,.．\n".to_string()),
             force: Some("".to_string()),
             parenthese_pairs: Some("「」,『』,(".to_string()),
+            abbreviations: Some("Dr., e.g.".to_string()),
+            delimiter_regex: None,
+            max_parenthese_length: None,
         };
         let splitter = creator.create().unwrap();
         assert_eq!(splitter.max_buf_length, 100);
@@ -237,6 +703,10 @@ mod tests {
             splitter.rev_parentheses,
             vec![('」', '「'), ('』', '『')].into_iter().collect()
         );
+        assert_eq!(
+            splitter.abbreviations,
+            HashSet::from_iter(vec!["Dr.".to_string(), "e.g.".to_string()])
+        );
     }
     #[test]
     fn test_to_sentence_splitter_default() {
@@ -245,6 +715,9 @@ mod tests {
             delimiter_chars: None,
             force: None,
             parenthese_pairs: None,
+            abbreviations: None,
+            delimiter_regex: None,
+            max_parenthese_length: None,
         };
         let splitter = creator.create().unwrap();
         assert_eq!(splitter.max_buf_length, 512);
@@ -365,6 +838,214 @@ mod tests {
         let sentences = splitter.split(text);
         assert_eq!(sentences, vec!["こ。", "れ。", "は。", "テストです", "。"]);
     }
+    #[test]
+    fn test_deserialize_without_abbreviations() {
+        let splitter = SentenceSplitterCreator::new(None, None, None, None)
+            .create()
+            .unwrap();
+        // serialized before abbreviations were added
+        let mut json = serde_json::to_value(&splitter).unwrap();
+        json.as_object_mut().unwrap().remove("abbreviations");
+        let deserialized: SentenceSplitter = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized, splitter);
+    }
+    #[test]
+    fn test_split_with_abbreviations() {
+        let splitter = SentenceSplitterCreator::new(None, Some(".!?".to_string()), None, None)
+            .create()
+            .unwrap();
+        let text = "Dr. Smith lives in the U.S. with Mrs. Smith. It costs 3.14 dollars, e.g. a coffee. E.g. tea is 2.5. Really?".to_string();
+        let expected = vec![
+            "Dr. Smith lives in the U.S. with Mrs. Smith.",
+            " It costs 3.14 dollars, e.g. a coffee.",
+            " E.g. tea is 2.5.",
+            " Really?",
+        ];
+        assert_eq!(splitter.split(text.clone()), expected);
+        assert_eq!(splitter.split_r(text), expected);
+        // case sensitive (not "No.")
+        let text = "I said no. Then left.".to_string();
+        assert_eq!(splitter.split(text), vec!["I said no.", " Then left."]);
+
+        // no abbreviations
+        let splitter = SentenceSplitterCreator {
+            abbreviations: Some("".to_string()),
+            ..SentenceSplitterCreator::new(None, Some(".".to_string()), None, None)
+        }
+        .create()
+        .unwrap();
+        let text = "Dr. Who. 1.5 hours.".to_string();
+        assert_eq!(splitter.split(text), vec!["Dr.", " Who.", " 1.5 hours."]);
+    }
+    #[test]
+    fn test_split_with_delimiter_regex() {
+        let splitter = SentenceSplitterCreator {
+            delimiter_regex: Some(r"[!?！？]+|\.{3}|…+".to_string()),
+            ..SentenceSplitterCreator::new(None, None, None, None)
+        }
+        .create()
+        .unwrap();
+        let text = "本当に？！はい…そうです。待って...行く!!終わり".to_string();
+        let expected = vec![
+            "本当に？！",
+            "はい…",
+            "そうです。",
+            "待って...",
+            "行く!!",
+            "終わり",
+        ];
+        assert_eq!(splitter.split(text.clone()), expected);
+        assert_eq!(splitter.split_r(text), expected);
+
+        // serialize as pattern string
+        let json = serde_json::to_string(&splitter).unwrap();
+        let deserialized: SentenceSplitter = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, splitter);
+
+        let creator = SentenceSplitterCreator {
+            delimiter_regex: Some("[".to_string()),
+            ..SentenceSplitterCreator::new(None, None, None, None)
+        };
+        assert!(creator.create().is_err());
+    }
+    #[test]
+    fn test_split_with_nested_parentheses() {
+        let splitter = SentenceSplitterCreator::new(None, None, None, None)
+            .create()
+            .unwrap();
+        // nested
+        let text = "「あ『い。』う。」え。お。".to_string();
+        assert_eq!(
+            splitter.split(text.clone()),
+            vec!["「あ『い。』う。」え。", "お。"]
+        );
+        assert_eq!(
+            splitter.split_r(text),
+            vec!["「あ『い。』う。」え。", "お。"]
+        );
+        // close with unclosed inner parenthese
+        let text = "「あ『い」う。え。".to_string();
+        assert_eq!(splitter.split(text.clone()), vec!["「あ『い」う。", "え。"]);
+        // never closed
+        let text = "「あい。うえ。お。".to_string();
+        assert_eq!(splitter.split(text), vec!["「あい。", "うえ。", "お。"]);
+        let text = "あ「い。『う。』え。".to_string();
+        assert_eq!(splitter.split(text), vec!["あ「い。", "『う。』え。"]);
+    }
+    #[test]
+    fn test_split_with_max_parenthese_length() {
+        let splitter = SentenceSplitterCreator {
+            max_parenthese_length: Some(5),
+            ..SentenceSplitterCreator::new(None, None, None, None)
+        }
+        .create()
+        .unwrap();
+        let text = "「あいう。」え。「あいうえおか。」き。".to_string();
+        assert_eq!(
+            splitter.split(text),
+            vec!["「あいう。」え。", "「あいうえおか。", "」き。"]
+        );
+    }
+    #[test]
+    fn test_split_with_same_char_parentheses() {
+        let splitter = SentenceSplitterCreator {
+            abbreviations: Some("".to_string()),
+            ..SentenceSplitterCreator::new(
+                None,
+                Some(".".to_string()),
+                None,
+                Some("\"\"".to_string()),
+            )
+        }
+        .create()
+        .unwrap();
+        let text = r#"He said "Go. Now." Then left. Bye."#.to_string();
+        assert_eq!(
+            splitter.split(text.clone()),
+            vec![r#"He said "Go. Now." Then left."#, " Bye."]
+        );
+        assert_eq!(
+            splitter.split_r(text),
+            vec![r#"He said "Go. Now." Then left."#, " Bye."]
+        );
+    }
+    #[test]
+    fn test_split_with_spans() {
+        let splitter = SentenceSplitterCreator::new(Some(9), None, None, None)
+            .create()
+            .unwrap();
+        let text = "これはテストです。「あれは？」テストではありません！";
+        let spans = splitter.split_with_spans(text);
+        assert_eq!(
+            spans,
+            vec![
+                (0..27, "これはテストです。"),
+                (27..54, "「あれは？」テスト"),
+                (54..78, "ではありません！")
+            ]
+        );
+        for (range, sentence) in spans {
+            assert_eq!(&text[range], sentence);
+        }
+
+        let splitter = SentenceSplitterCreator::new(None, Some(".".to_string()), None, None)
+            .create()
+            .unwrap();
+        assert_eq!(
+            splitter.split_with_spans("Dr. Who. 1.5 hours"),
+            vec![(0..8, "Dr. Who."), (8..18, " 1.5 hours")]
+        );
+        assert_eq!(splitter.split_with_spans(""), vec![]);
+    }
+    #[test]
+    fn test_split_iter() {
+        let splitter = SentenceSplitterCreator::new(None, None, None, None)
+            .create()
+            .unwrap();
+        let text = "これはテストです。あれは\nテストではありません。「括弧\nの中」です。\n最後"
+            .to_string();
+        let reader = std::io::BufReader::with_capacity(4, text.as_bytes());
+        let mut iter = splitter.split_iter(reader);
+        assert_eq!(iter.next(), Some("これはテストです。".to_string()));
+        assert_eq!(iter.next(), Some("あれは\n".to_string()));
+        assert_eq!(iter.next(), Some("テストではありません。".to_string()));
+        assert_eq!(iter.next(), Some("「括弧\nの中」です。".to_string()));
+        assert_eq!(iter.next(), Some("\n".to_string()));
+        assert_eq!(iter.next(), Some("最後".to_string()));
+        assert_eq!(iter.next(), None);
+
+        let reader = std::io::BufReader::new(text.as_bytes());
+        assert_eq!(
+            splitter.split_iter(reader).collect::<Vec<_>>(),
+            splitter.split(text.clone())
+        );
+        assert_eq!(splitter.split_iter(std::io::empty()).next(), None);
+    }
+    #[test]
+    fn test_split_iter_long_sentence() {
+        let mut creator = SentenceSplitterCreator::new(Some(100), None, None, None);
+        creator.max_parenthese_length = Some(30);
+        let splitter = creator.create().unwrap();
+        // unclosed parenthese continues over many lines
+        let text = format!("前置き。「{}閉じない。\n最後", "あいう、\n".repeat(50));
+        let reader = std::io::BufReader::new(text.as_bytes());
+        let sentences = splitter.split_iter(reader).collect::<Vec<_>>();
+        assert_eq!(sentences, splitter.split(text.clone()));
+        assert_eq!(sentences[0], "前置き。");
+        assert!(sentences.iter().all(|s| s.chars().count() <= 100));
+        assert_eq!(sentences.concat(), text);
+
+        // period at the end of a line is divided by the next char
+        let mut creator = SentenceSplitterCreator::new(None, Some(".\n".to_string()), None, None);
+        creator.abbreviations = Some("e.g.".to_string());
+        let splitter = creator.create().unwrap();
+        let text = "Pi is 3.\n14 e.g.\nfoo.".to_string();
+        let reader = std::io::BufReader::new(text.as_bytes());
+        assert_eq!(
+            splitter.split_iter(reader).collect::<Vec<_>>(),
+            splitter.split(text.clone())
+        );
+    }
     // XXX now using and testing dividing timed text only
     #[test]
     fn test_split_with_div_regex() {
@@ -411,5 +1092,52 @@ mod tests {
         assert_eq!(TextUtil::snake_to_camel("snakeToCamel"), "SnakeToCamel");
         assert_eq!(TextUtil::snake_to_camel("snake?"), "Snake?");
         assert_eq!(TextUtil::snake_to_camel("SNAKE_TO_CAMEL"), "SNAKETOCAMEL"); // XXX
+                                                                                // acronyms and other separators are kept
+        assert_eq!(TextUtil::snake_to_camel("my_HTTP_server"), "MyHTTPServer");
+        assert_eq!(TextUtil::snake_to_camel("parse_JSON"), "ParseJSON");
+        assert_eq!(
+            TextUtil::snake_to_camel("snake-case name"),
+            "Snake-case name"
+        );
+        assert_eq!(TextUtil::to_pascal("my_HTTP_server"), "MyHttpServer");
+    }
+
+    #[test]
+    fn test_generate_secure_key() {
+        let key = TextUtil::generate_secure_key(22);
+        assert_eq!(key.len(), 22);
+        assert!(key.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_ne!(key, TextUtil::generate_secure_key(22));
+        let key = TextUtil::generate_secure_key_with(32, KeyAlphabet::Base32);
+        assert!(key
+            .bytes()
+            .all(|c| KeyAlphabet::Base32.chars().contains(&c)));
+
+        assert_eq!(TextUtil::ulid_from_parts(0, 0), "0".repeat(26));
+        assert_eq!(
+            TextUtil::ulid_from_parts(1469918176385, u128::MAX),
+            "01ARYZ6S41ZZZZZZZZZZZZZZZZ"
+        );
+        let ulid = TextUtil::generate_ulid();
+        assert_eq!(ulid.len(), 26);
+        // ordered by the timestamp part (random part in the same millisecond)
+        assert!(ulid[..10] <= TextUtil::generate_ulid()[..10]);
+    }
+
+    #[test]
+    fn test_case_conversion() {
+        assert_eq!(
+            TextUtil::split_words("HTTPServerV2_config-name utf8Value"),
+            vec!["HTTP", "Server", "V2", "config", "name", "utf8", "Value"]
+        );
+        assert_eq!(TextUtil::camel_to_snake("HTTPServer"), "http_server");
+        assert_eq!(TextUtil::camel_to_snake("snakeToCamel"), "snake_to_camel");
+        assert_eq!(TextUtil::to_kebab("SnakeToCamel"), "snake-to-camel");
+        assert_eq!(TextUtil::to_pascal("http_server"), "HttpServer");
+        assert_eq!(TextUtil::to_pascal("ÉCOLE_NAME"), "ÉcoleName");
+        assert_eq!(TextUtil::to_screaming_snake("httpServer"), "HTTP_SERVER");
+        assert_eq!(TextUtil::to_screaming_snake("kebab-case"), "KEBAB_CASE");
+        // uncased letters are followed by a boundary before an uppercase letter
+        assert_eq!(TextUtil::camel_to_snake("日本語Name"), "日本語_name");
     }
 }
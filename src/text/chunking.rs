@@ -0,0 +1,197 @@
+use super::TokenProvider;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// split text recursively by an ordered list of separators (LangChain RecursiveCharacterTextSplitter like)
+///
+/// text is split by the first separator found in it, pieces not shorter than `chunk_size`
+/// are split again by the rest separators ("" splits into chars), and the pieces are merged
+/// into chunks of at most `chunk_size` tokens with `chunk_overlap` tokens of the previous chunk.
+///
+/// - sizes are the sum of the token counts of the pieces (counted by `TokenProvider`)
+/// - separators are kept at the end of the preceding piece (ex. "。")
+/// - chunks are trimmed if `strip_whitespace` and empty chunks are dropped
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct RecursiveCharacterSplitter {
+    pub chunk_size: usize,
+    pub chunk_overlap: usize,
+    pub separators: Vec<String>,
+    pub strip_whitespace: bool,
+}
+
+impl RecursiveCharacterSplitter {
+    pub const DEFAULT_SEPARATORS: [&'static str; 5] = ["\n\n", "\n", "。", " ", ""];
+
+    pub fn new(chunk_size: usize, chunk_overlap: usize) -> Result<Self> {
+        if chunk_size == 0 {
+            return Err(anyhow!("chunk_size must be greater than 0"));
+        }
+        if chunk_overlap > chunk_size {
+            return Err(anyhow!(
+                "chunk_overlap ({}) must not be larger than chunk_size ({})",
+                chunk_overlap,
+                chunk_size
+            ));
+        }
+        Ok(Self {
+            chunk_size,
+            chunk_overlap,
+            separators: Self::DEFAULT_SEPARATORS
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            strip_whitespace: true,
+        })
+    }
+
+    pub fn with_separators(self, separators: Vec<String>) -> Self {
+        Self { separators, ..self }
+    }
+
+    pub fn split_text(&self, text: &str, provider: &impl TokenProvider) -> Result<Vec<String>> {
+        self.split_recursive(text, &self.separators, provider)
+    }
+
+    fn split_recursive(
+        &self,
+        text: &str,
+        separators: &[String],
+        provider: &impl TokenProvider,
+    ) -> Result<Vec<String>> {
+        // first separator in the text ("" always matches)
+        let (separator, rest) = separators
+            .iter()
+            .position(|s| s.is_empty() || text.contains(s.as_str()))
+            .map(|i| (separators[i].as_str(), &separators[i + 1..]))
+            .unwrap_or(("", &[]));
+
+        let mut chunks = Vec::new();
+        // (piece, token count) shorter than chunk_size
+        let mut good: Vec<(&str, usize)> = Vec::new();
+        for piece in split_keep_separator(text, separator) {
+            let count = provider.estimate_token_count(piece)?;
+            if count < self.chunk_size {
+                good.push((piece, count));
+                continue;
+            }
+            if !good.is_empty() {
+                chunks.extend(self.merge_pieces(&std::mem::take(&mut good)));
+            }
+            if rest.is_empty() {
+                // cannot split any more
+                chunks.extend(self.to_chunk(piece));
+            } else {
+                chunks.extend(self.split_recursive(piece, rest, provider)?);
+            }
+        }
+        if !good.is_empty() {
+            chunks.extend(self.merge_pieces(&good));
+        }
+        Ok(chunks)
+    }
+
+    fn merge_pieces(&self, pieces: &[(&str, usize)]) -> Vec<String> {
+        let mut chunks = Vec::new();
+        // range of pieces in the current chunk
+        let mut start = 0;
+        let mut total = 0;
+        for (i, &(_, count)) in pieces.iter().enumerate() {
+            if total + count > self.chunk_size && start < i {
+                chunks.extend(self.join_pieces(&pieces[start..i]));
+                // keep the last pieces within chunk_overlap for the next chunk
+                while start < i && (total > self.chunk_overlap || total + count > self.chunk_size) {
+                    total -= pieces[start].1;
+                    start += 1;
+                }
+            }
+            total += count;
+        }
+        if start < pieces.len() {
+            chunks.extend(self.join_pieces(&pieces[start..]));
+        }
+        chunks
+    }
+
+    fn join_pieces(&self, pieces: &[(&str, usize)]) -> Option<String> {
+        self.to_chunk(&pieces.iter().map(|(p, _)| *p).collect::<String>())
+    }
+
+    fn to_chunk(&self, text: &str) -> Option<String> {
+        let chunk = if self.strip_whitespace {
+            text.trim()
+        } else {
+            text
+        };
+        (!chunk.is_empty()).then(|| chunk.to_string())
+    }
+}
+
+// split text after each separator ("": split into chars)
+fn split_keep_separator<'a>(text: &'a str, separator: &str) -> Vec<&'a str> {
+    if separator.is_empty() {
+        return text
+            .char_indices()
+            .map(|(i, c)| &text[i..i + c.len_utf8()])
+            .collect();
+    }
+    text.split_inclusive(separator)
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1 char = 1 token
+    struct CharTokenizer;
+    impl TokenProvider for CharTokenizer {
+        fn tokenize(&self, text: &str) -> Result<Vec<u32>> {
+            Ok(text.chars().map(|c| c as u32).collect())
+        }
+    }
+
+    #[test]
+    fn test_recursive_character_splitter() -> Result<()> {
+        let splitter = RecursiveCharacterSplitter::new(8, 0)?;
+        assert_eq!(
+            splitter.split_text("aaa bbb ccc\n\nddd eee", &CharTokenizer)?,
+            vec!["aaa bbb", "ccc", "ddd eee"]
+        );
+        // split into sentences by "。"
+        assert_eq!(
+            splitter.split_text("今日は晴れ。明日は雨。", &CharTokenizer)?,
+            vec!["今日は晴れ。", "明日は雨。"]
+        );
+        // no separator: split into chars
+        let splitter = RecursiveCharacterSplitter::new(4, 0)?;
+        assert_eq!(
+            splitter.split_text("abcdefghij", &CharTokenizer)?,
+            vec!["abcd", "efgh", "ij"]
+        );
+        assert!(splitter.split_text("", &CharTokenizer)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_recursive_character_splitter_overlap() -> Result<()> {
+        let splitter = RecursiveCharacterSplitter::new(8, 4)?;
+        assert_eq!(
+            splitter.split_text("aaa bbb ccc ddd", &CharTokenizer)?,
+            vec!["aaa bbb", "bbb ccc", "ccc ddd"]
+        );
+        let splitter =
+            RecursiveCharacterSplitter::new(8, 4)?.with_separators(vec![",".to_string()]);
+        assert_eq!(
+            splitter.split_text("a,bb,cccccccccc,d", &CharTokenizer)?,
+            vec!["a,bb,", "cccccccccc,", "d"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_recursive_character_splitter_invalid() {
+        assert!(RecursiveCharacterSplitter::new(0, 0).is_err());
+        assert!(RecursiveCharacterSplitter::new(4, 5).is_err());
+    }
+}
@@ -0,0 +1,429 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use unicode_normalization::UnicodeNormalization;
+
+/// pre-processor for text extracted from pdf (before sentence splitting or chunking)
+///
+/// - joins lines broken by page layout into paragraphs (blank lines are kept as paragraph boundaries)
+/// - removes hyphenation at line ends (ex. "hyphen-\nation" -> "hyphenation")
+/// - removes headers/footers repeated on pages (pages are separated by form feed '\x0c')
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct PdfTextNormalizer {
+    pub join_broken_lines: bool,
+    pub remove_hyphenation: bool,
+    pub remove_headers_and_footers: bool,
+    /// number of lines at the top and bottom of each page checked as header/footer
+    pub header_footer_lines: usize,
+    /// lines repeated on at least this number of pages are removed as header/footer
+    pub min_repeat_pages: usize,
+    /// a line ending with these chars is not joined with the next line
+    pub sentence_end_chars: String,
+}
+
+impl Default for PdfTextNormalizer {
+    fn default() -> Self {
+        Self {
+            join_broken_lines: true,
+            remove_hyphenation: true,
+            remove_headers_and_footers: true,
+            header_footer_lines: 2,
+            min_repeat_pages: 2,
+            sentence_end_chars: Self::SENTENCE_END_CHARS.to_string(),
+        }
+    }
+}
+
+impl PdfTextNormalizer {
+    pub const SENTENCE_END_CHARS: &'static str = "。．！？.!?:：」』";
+    const PAGE_SEPARATOR: char = '\x0c';
+
+    pub fn normalize(&self, text: &str) -> String {
+        let text = text.replace("\r\n", "\n");
+        let mut pages: Vec<Vec<&str>> = text
+            .split(Self::PAGE_SEPARATOR)
+            .map(|page| page.lines().collect())
+            .collect();
+        if self.remove_headers_and_footers && pages.len() > 1 {
+            self.remove_repeated_lines(&mut pages);
+        }
+        let lines = pages.into_iter().flatten();
+
+        let mut paragraphs: Vec<String> = Vec::new();
+        let mut current: Option<String> = None;
+        for line in lines {
+            let line = line.trim_end();
+            if line.trim().is_empty() {
+                if let Some(c) = current.take() {
+                    paragraphs.push(c);
+                }
+                // collapse blank lines
+                if paragraphs.last().is_some_and(|p| !p.is_empty()) {
+                    paragraphs.push(String::new());
+                }
+                continue;
+            }
+            match current.as_mut() {
+                Some(c) if self.join_broken_lines && self.should_join(c, line) => {
+                    self.join_line(c, line.trim_start())
+                }
+                _ => {
+                    if let Some(c) = current.take() {
+                        paragraphs.push(c);
+                    }
+                    current = Some(line.to_string());
+                }
+            }
+        }
+        if let Some(c) = current.take() {
+            paragraphs.push(c);
+        }
+        while paragraphs.last().is_some_and(|p| p.is_empty()) {
+            paragraphs.pop();
+        }
+        paragraphs.join("\n")
+    }
+
+    fn should_join(&self, prev: &str, next: &str) -> bool {
+        match prev.chars().last() {
+            Some(c) if self.sentence_end_chars.contains(c) => false,
+            Some(_) => !Self::is_list_item(next.trim_start()),
+            None => false,
+        }
+    }
+
+    fn join_line(&self, prev: &mut String, next: &str) {
+        let mut prev_chars = prev.chars().rev();
+        let last = prev_chars.next();
+        let before_last = prev_chars.next();
+        let first = next.chars().next();
+        match (before_last, last, first) {
+            (Some(b), Some('-'), Some(f))
+                if self.remove_hyphenation && b.is_alphabetic() && f.is_lowercase() =>
+            {
+                prev.pop();
+                prev.push_str(next);
+            }
+            (_, Some(l), Some(f)) if is_cjk(l) || is_cjk(f) => prev.push_str(next),
+            _ => {
+                prev.push(' ');
+                prev.push_str(next);
+            }
+        }
+    }
+
+    // bullet or numbered list ("・", "- ", "1. ", "(1)", ...)
+    fn is_list_item(line: &str) -> bool {
+        let mut chars = line.chars();
+        match chars.next() {
+            Some('・' | '•' | '●' | '■' | '※') => true,
+            Some('-' | '*' | '+') => chars.next() == Some(' '),
+            Some('(' | '（') => chars.next().is_some_and(|c| c.is_ascii_digit()),
+            Some(c) if c.is_ascii_digit() => {
+                // whitespace after the marker (not a decimal number such as "3.14")
+                let mut rest = line
+                    .trim_start_matches(|c: char| c.is_ascii_digit())
+                    .chars();
+                matches!(rest.next(), Some('.' | ')'))
+                    && rest.next().is_some_and(char::is_whitespace)
+            }
+            _ => false,
+        }
+    }
+
+    // remove lines repeated at the top or bottom of pages (page numbers are matched ignoring digits)
+    // (ex. "- 1 -", "Page 2 of 10")
+    fn remove_repeated_lines(&self, pages: &mut [Vec<&str>]) {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for page in pages.iter() {
+            let mut keys: Vec<String> = self
+                .edge_line_indices(page)
+                .into_iter()
+                .map(|i| Self::header_footer_key(page[i]))
+                .collect();
+            // count once per page
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                *counts.entry(key).or_default() += 1;
+            }
+        }
+        for page in pages.iter_mut() {
+            let remove: Vec<usize> = self
+                .edge_line_indices(page)
+                .into_iter()
+                .filter(|i| {
+                    counts
+                        .get(&Self::header_footer_key(page[*i]))
+                        .is_some_and(|c| *c >= self.min_repeat_pages)
+                })
+                .collect();
+            let mut index = 0;
+            page.retain(|_| {
+                let keep = !remove.contains(&index);
+                index += 1;
+                keep
+            });
+        }
+    }
+
+    // indices of first and last non empty lines in the page
+    fn edge_line_indices(&self, page: &[&str]) -> Vec<usize> {
+        let non_empty: Vec<usize> = page
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+            .map(|(i, _)| i)
+            .collect();
+        let n = self.header_footer_lines;
+        let mut indices: Vec<usize> = non_empty.iter().take(n).cloned().collect();
+        indices.extend(non_empty.iter().rev().take(n));
+        indices.sort();
+        indices.dedup();
+        indices
+    }
+
+    // words allowed in page number lines besides digits and symbols
+    const PAGE_NUMBER_WORDS: [&'static str; 5] = ["page", "p", "of", "ページ", "頁"];
+
+    fn is_page_number(line: &str) -> bool {
+        line.chars().any(|c| c.is_ascii_digit())
+            && line
+                .split(|c: char| !c.is_alphanumeric() || c.is_ascii_digit())
+                .filter(|w| !w.is_empty())
+                .all(|w| Self::PAGE_NUMBER_WORDS.contains(&w.to_lowercase().as_str()))
+    }
+
+    fn header_footer_key(line: &str) -> String {
+        let line = line.trim();
+        if !Self::is_page_number(line) {
+            return line.to_string();
+        }
+        let mut key = String::new();
+        for c in line.chars() {
+            if c.is_ascii_digit() {
+                if !key.ends_with('#') {
+                    key.push('#');
+                }
+            } else {
+                key.push(c);
+            }
+        }
+        key
+    }
+}
+
+/// normalize pdf extracted text with default settings
+pub fn normalize_pdf_text(text: &str) -> String {
+    PdfTextNormalizer::default().normalize(text)
+}
+
+/// unicode normalization form
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub enum UnicodeForm {
+    Nfc,
+    Nfkc,
+}
+
+/// width conversion of ascii chars and space (use NFKC for half-width katakana)
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub enum WidthConversion {
+    Half,
+    Full,
+}
+
+/// canonicalizer for input text (before sentence splitting or chunking)
+///
+/// applied in order: control chars stripping, unicode normalization, width conversion, whitespace collapsing
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct TextNormalizer {
+    pub unicode_form: Option<UnicodeForm>,
+    pub width: Option<WidthConversion>,
+    pub collapse_whitespace: bool,
+    pub strip_control_chars: bool,
+}
+
+impl Default for TextNormalizer {
+    fn default() -> Self {
+        Self {
+            unicode_form: Some(UnicodeForm::Nfc),
+            width: None,
+            collapse_whitespace: true,
+            strip_control_chars: true,
+        }
+    }
+}
+
+impl TextNormalizer {
+    pub fn normalize(&self, text: &str) -> String {
+        let mut text = if self.strip_control_chars {
+            strip_control_chars(text)
+        } else {
+            text.to_string()
+        };
+        text = match self.unicode_form {
+            Some(UnicodeForm::Nfc) => nfc(&text),
+            Some(UnicodeForm::Nfkc) => nfkc(&text),
+            None => text,
+        };
+        text = match self.width {
+            Some(WidthConversion::Half) => to_half_width(&text),
+            Some(WidthConversion::Full) => to_full_width(&text),
+            None => text,
+        };
+        if self.collapse_whitespace {
+            text = collapse_whitespace(&text);
+        }
+        text
+    }
+}
+
+pub fn nfc(text: &str) -> String {
+    text.nfc().collect()
+}
+
+pub fn nfkc(text: &str) -> String {
+    text.nfkc().collect()
+}
+
+/// full-width ascii chars and ideographic space to half-width ("ＡＢＣ　１２３" -> "ABC 123")
+pub fn to_half_width(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            '\u{3000}' => ' ',
+            _ => c,
+        })
+        .collect()
+}
+
+/// half-width ascii chars and space to full-width ("ABC 123" -> "ＡＢＣ　１２３")
+pub fn to_full_width(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '!'..='~' => char::from_u32(c as u32 + 0xFEE0).unwrap_or(c),
+            ' ' => '\u{3000}',
+            _ => c,
+        })
+        .collect()
+}
+
+/// collapse whitespace runs in each line into a single space, trim lines and collapse blank lines
+pub fn collapse_whitespace(text: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() && lines.last().map(String::is_empty).unwrap_or(true) {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+/// remove control chars except newline and tab, and zero width chars (ex. BOM)
+pub fn strip_control_chars(text: &str) -> String {
+    text.chars()
+        .filter(|c| {
+            !((c.is_control() && *c != '\n' && *c != '\t')
+                || matches!(c, '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}'))
+        })
+        .collect()
+}
+
+// hiragana, katakana, cjk ideographs, cjk symbols and fullwidth forms
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3000}'..='\u{303F}'
+        | '\u{3040}'..='\u{309F}'
+        | '\u{30A0}'..='\u{30FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FF00}'..='\u{FFEF}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_broken_lines() {
+        let text = "This is a sen-\ntence broken by\nlayout.\n\nNext paragraph\nhere.\n";
+        assert_eq!(
+            normalize_pdf_text(text),
+            "This is a sentence broken by layout.\n\nNext paragraph here."
+        );
+        let text = "吾輩は猫で\nある。名前は\nまだ無い。\n・項目1\n・項目2";
+        assert_eq!(
+            normalize_pdf_text(text),
+            "吾輩は猫である。名前はまだ無い。\n・項目1\n・項目2"
+        );
+        // keep hyphen of compound words
+        let text = "state-\nOf the art";
+        assert_eq!(normalize_pdf_text(text), "state- Of the art");
+        // decimal number is not a list item
+        let text = "The ratio is\n3.14 in this case.\n1. first\n2) second";
+        assert_eq!(
+            normalize_pdf_text(text),
+            "The ratio is 3.14 in this case.\n1. first\n2) second"
+        );
+    }
+
+    #[test]
+    fn test_unicode_normalization() {
+        assert_eq!(nfc("e\u{301}"), "\u{e9}");
+        assert_eq!(nfkc("ｶﾞｷﾞ①ＡＢＣ"), "ガギ1ABC");
+    }
+
+    #[test]
+    fn test_width_conversion() {
+        assert_eq!(to_half_width("ＡＢＣ　１２３！"), "ABC 123!");
+        assert_eq!(to_full_width("ABC 123!"), "ＡＢＣ　１２３！");
+        assert_eq!(to_half_width("全角カナ"), "全角カナ");
+    }
+
+    #[test]
+    fn test_text_normalizer() {
+        assert_eq!(
+            collapse_whitespace("  a \t b  \n\n\n\u{3000}c\n\n"),
+            "a b\n\nc"
+        );
+        assert_eq!(strip_control_chars("\u{FEFF}a\u{0}b\r\n\tc"), "ab\n\tc");
+        let normalizer = TextNormalizer {
+            width: Some(WidthConversion::Half),
+            ..Default::default()
+        };
+        assert_eq!(
+            normalizer.normalize("\u{FEFF}ＡＩ　と\r\n\r\n\r\n機械学習\u{7}"),
+            "AI と\n\n機械学習"
+        );
+    }
+
+    #[test]
+    fn test_remove_headers_and_footers() {
+        let text = "Annual Report\nfirst page body\ncontinues.\n- 1 -\n\x0cAnnual Report\nsecond page\nbody.\n- 2 -\n";
+        assert_eq!(
+            normalize_pdf_text(text),
+            "first page body continues.\nsecond page body."
+        );
+        let normalizer = PdfTextNormalizer {
+            remove_headers_and_footers: false,
+            join_broken_lines: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            normalizer.normalize(text),
+            "Annual Report\nfirst page body\ncontinues.\n- 1 -\nAnnual Report\nsecond page\nbody.\n- 2 -"
+        );
+        // numbered headings are not page numbers
+        let text =
+            "Chapter 1\n\nfirst body.\nPage 1 of 2\n\x0cChapter 2\n\nsecond body.\nPage 2 of 2\n";
+        assert_eq!(
+            normalize_pdf_text(text),
+            "Chapter 1\n\nfirst body.\nChapter 2\n\nsecond body."
+        );
+    }
+}
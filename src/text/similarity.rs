@@ -0,0 +1,137 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+
+/// char k-grams of the text (whitespace runs are treated as a single space)
+///
+/// text shorter than k is returned as a single shingle
+pub fn shingles(text: &str, k: usize) -> HashSet<String> {
+    let chars: Vec<char> = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .collect();
+    if chars.is_empty() {
+        return HashSet::new();
+    }
+    let k = k.max(1);
+    if chars.len() <= k {
+        return HashSet::from([chars.iter().collect()]);
+    }
+    chars.windows(k).map(|w| w.iter().collect()).collect()
+}
+
+/// jaccard similarity of two sets (1.0 if both are empty)
+pub fn jaccard<T: Eq + Hash>(a: &HashSet<T>, b: &HashSet<T>) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let intersection = a.intersection(b).count();
+    intersection as f64 / (a.len() + b.len() - intersection) as f64
+}
+
+/// jaccard similarity over char k-gram shingles
+pub fn shingle_jaccard(a: &str, b: &str, k: usize) -> f64 {
+    jaccard(&shingles(a, k), &shingles(b, k))
+}
+
+/// 64bit simhash over char k-gram shingles (stable across processes: uses FNV-1a)
+pub fn simhash(text: &str, k: usize) -> u64 {
+    let mut weights = [0i64; 64];
+    for shingle in shingles(text, k) {
+        let hash = fnv1a64(shingle.as_bytes());
+        for (i, w) in weights.iter_mut().enumerate() {
+            if hash & (1 << i) != 0 {
+                *w += 1;
+            } else {
+                *w -= 1;
+            }
+        }
+    }
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, w)| **w > 0)
+        .fold(0u64, |acc, (i, _)| acc | (1 << i))
+}
+
+/// number of different bits of two simhash values
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// levenshtein distance in chars
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    levenshtein_within(a, b, usize::MAX).unwrap_or(usize::MAX)
+}
+
+/// levenshtein distance in chars if it is at most max_distance, otherwise None
+///
+/// exits early when the length difference or the minimum of a row exceeds max_distance
+pub fn levenshtein_within(a: &str, b: &str, max_distance: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max_distance {
+        return None;
+    }
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        let mut row_min = current[0];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            current[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(current[j] + 1);
+            row_min = row_min.min(current[j + 1]);
+        }
+        if row_min > max_distance {
+            return None;
+        }
+        std::mem::swap(&mut prev, &mut current);
+    }
+    Some(prev[b.len()]).filter(|d| *d <= max_distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shingle_jaccard() {
+        assert_eq!(shingles("abcd", 2).len(), 3);
+        assert_eq!(shingles("a", 3), HashSet::from(["a".to_string()]));
+        assert_eq!(shingle_jaccard("abcd", "abcd", 2), 1.0);
+        assert_eq!(shingle_jaccard("abcd", "wxyz", 2), 0.0);
+        // {ab, bc, cd} vs {ab, bc, ce}
+        assert_eq!(shingle_jaccard("abcd", "abce", 2), 0.5);
+        assert_eq!(shingle_jaccard("a  b", "a b", 2), 1.0);
+    }
+
+    #[test]
+    fn test_simhash() {
+        let text = "吾輩は猫である。名前はまだ無い。どこで生れたかとんと見当がつかぬ。";
+        let near = "吾輩は猫である。名前はまだ無い。どこで生まれたかとんと見当がつかぬ。";
+        let other = "The quick brown fox jumps over the lazy dog.";
+        assert_eq!(simhash(text, 3), simhash(text, 3));
+        assert!(
+            hamming_distance(simhash(text, 3), simhash(near, 3))
+                < hamming_distance(simhash(text, 3), simhash(other, 3))
+        );
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("猫である", "犬である"), 1);
+        assert_eq!(levenshtein_within("kitten", "sitting", 3), Some(3));
+        assert_eq!(levenshtein_within("kitten", "sitting", 2), None);
+        assert_eq!(levenshtein_within("a", "abcdef", 2), None);
+    }
+}
@@ -384,16 +384,331 @@ pub mod datetime {
     }
 }
 pub mod text {
-    use super::option::FlatMap;
+    use crate::text::TokenProvider;
     use anyhow::{anyhow, Result};
+    use once_cell::sync::Lazy;
     use regex::Regex;
+    use std::ops::Range;
+    use unicode_segmentation::UnicodeSegmentation;
 
     // https://stackoverflow.com/a/6041965
     const URL_REGEX: &str = r"((?:http|ftp|https):\/\/(:?[\w_-]+(?:(?:\.[\w_-]+)+))(?:[\w.,@?^=%&:\/~+#-]*[\w@?^=%&\/~+#-]))";
+    static URL_RE: Lazy<Regex> = Lazy::new(|| Regex::new(URL_REGEX).unwrap());
+    // punctuation chars appended to urls with include_trailing_punctuation
+    const TRAILING_PUNCTUATION: &[char] = &['.', ',', ':', ';', '!', '?', ')'];
+
     pub fn extract_url_simple(message: &str) -> Option<&str> {
-        let re = Regex::new(URL_REGEX).unwrap();
-        re.captures(message)
-            .flat_map(|c| c.get(1).map(|s| s.as_str()))
+        extract_urls(message).into_iter().next().map(|(_, url)| url)
+    }
+    /// 文字列中の全てのURLを位置(バイト範囲)とともに返す (末尾の句読点は含まない)
+    pub fn extract_urls(text: &str) -> Vec<(Range<usize>, &str)> {
+        extract_urls_with_options(text, false)
+    }
+    /// 文字列中の全てのURLを位置(バイト範囲)とともに返す
+    ///
+    /// # Arguments
+    /// * `text` - 対象の文字列
+    /// * `include_trailing_punctuation` - URL直後の句読点 (`.,:;!?)`) をURLに含めるか
+    pub fn extract_urls_with_options(
+        text: &str,
+        include_trailing_punctuation: bool,
+    ) -> Vec<(Range<usize>, &str)> {
+        URL_RE
+            .find_iter(text)
+            .map(|m| {
+                let mut end = m.end();
+                if include_trailing_punctuation {
+                    end += text[end..]
+                        .chars()
+                        .take_while(|c| TRAILING_PUNCTUATION.contains(c))
+                        .map(char::len_utf8)
+                        .sum::<usize>();
+                }
+                (m.start()..end, &text[m.start()..end])
+            })
+            .collect()
+    }
+    // markdown syntax (applied in order by strip_markdown)
+    static MD_REF_DEF_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r#"(?m)^ {0,3}\[[^\]]+\]:\s+\S+.*(?:\n|$)"#).unwrap());
+    static MD_HR_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?m)^ {0,3}(?:[-*_][ \t]*){3,}$").unwrap());
+    static MD_HEADING_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?m)^ {0,3}#{1,6}[ \t]+").unwrap());
+    static MD_QUOTE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^ {0,3}(?:>[ \t]?)+").unwrap());
+    static MD_BULLET_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?m)^([ \t]*)[-*+][ \t]+").unwrap());
+    static MD_IMAGE_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r#"!\[([^\]]*)\]\(([^)\s]*)(?:\s+"[^"]*")?\)"#).unwrap());
+    static MD_LINK_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r#"\[([^\]]*)\]\(([^)\s]*)(?:\s+"[^"]*")?\)"#).unwrap());
+    static MD_REF_LINK_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"\[([^\]]+)\]\[[^\]]*\]").unwrap());
+    static MD_INLINE_CODE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"`([^`\n]+)`").unwrap());
+    static MD_EMPHASIS_RES: Lazy<Vec<Regex>> = Lazy::new(|| {
+        [
+            r"\*\*([^*\n]+)\*\*",
+            r"~~([^~\n]+)~~",
+            r"\*([^*\s](?:[^*\n]*[^*\s])?)\*",
+        ]
+        .iter()
+        .map(|r| Regex::new(r).unwrap())
+        .collect()
+    });
+    // underscore emphasis only at word boundaries (not in snake_case words)
+    static MD_UNDERSCORE_EMPHASIS_RES: Lazy<Vec<Regex>> = Lazy::new(|| {
+        [
+            r"(^|\W)__([^_\n]+)__(\W|$)",
+            r"(^|\W)_([^_\s](?:[^_\n]*[^_\s])?)_(\W|$)",
+        ]
+        .iter()
+        .map(|r| Regex::new(r).unwrap())
+        .collect()
+    });
+
+    /// markdownの記法を取り除いてプレーンテキストにする (リンクはアンカーテキストを残す)
+    pub fn strip_markdown(text: &str) -> String {
+        strip_markdown_with_options(text, false)
+    }
+    /// markdownの記法を取り除いてプレーンテキストにする
+    ///
+    /// 強調・見出し・引用・箇条書きの記号、画像、コードブロック(フェンス内も含む)を取り除き、
+    /// インラインコードは内容のみ残す
+    ///
+    /// # Arguments
+    /// * `text` - markdown文字列
+    /// * `keep_link_url` - リンクをアンカーテキストではなくURLに置き換えるか
+    pub fn strip_markdown_with_options(text: &str, keep_link_url: bool) -> String {
+        // remove fenced code blocks
+        let mut lines = Vec::new();
+        let mut fence: Option<&str> = None;
+        for line in text.lines() {
+            let trimmed = line.trim_start();
+            match fence {
+                Some(f) if trimmed.starts_with(f) => fence = None,
+                Some(_) => {}
+                None if trimmed.starts_with("```") => fence = Some("```"),
+                None if trimmed.starts_with("~~~") => fence = Some("~~~"),
+                None => lines.push(line),
+            }
+        }
+        let text = lines.join("\n");
+
+        let text = MD_REF_DEF_RE.replace_all(&text, "");
+        let text = MD_HR_RE.replace_all(&text, "");
+        let text = MD_HEADING_RE.replace_all(&text, "");
+        let text = MD_QUOTE_RE.replace_all(&text, "");
+        let text = MD_BULLET_RE.replace_all(&text, "${1}");
+        let text = MD_IMAGE_RE.replace_all(&text, "");
+        let text = MD_LINK_RE.replace_all(&text, if keep_link_url { "${2}" } else { "${1}" });
+        let text = MD_REF_LINK_RE.replace_all(&text, "${1}");
+        let text = MD_INLINE_CODE_RE.replace_all(&text, "${1}");
+        let mut text = text.into_owned();
+        for re in MD_EMPHASIS_RES.iter() {
+            text = re.replace_all(&text, "${1}").into_owned();
+        }
+        for re in MD_UNDERSCORE_EMPHASIS_RES.iter() {
+            text = re.replace_all(&text, "${1}${2}${3}").into_owned();
+        }
+        text.trim().to_string()
+    }
+    /// html_to_textのオプション
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct HtmlToTextOptions {
+        /// ブロック要素 (p, div, br, li, h1等) の境界で改行するか
+        pub block_newlines: bool,
+        /// 連続する空白を1つのスペースにまとめるか (pre内は除く)
+        pub collapse_whitespace: bool,
+    }
+    impl Default for HtmlToTextOptions {
+        fn default() -> Self {
+            Self {
+                block_newlines: true,
+                collapse_whitespace: true,
+            }
+        }
+    }
+    // elements whose content is not text
+    const HTML_SKIP_TAGS: &[&str] = &["head", "script", "style", "noscript", "template"];
+    const HTML_BLOCK_TAGS: &[&str] = &[
+        "address",
+        "article",
+        "aside",
+        "blockquote",
+        "dd",
+        "div",
+        "dl",
+        "dt",
+        "fieldset",
+        "figcaption",
+        "figure",
+        "footer",
+        "form",
+        "h1",
+        "h2",
+        "h3",
+        "h4",
+        "h5",
+        "h6",
+        "header",
+        "hr",
+        "li",
+        "main",
+        "nav",
+        "ol",
+        "p",
+        "pre",
+        "section",
+        "table",
+        "td",
+        "th",
+        "tr",
+        "ul",
+    ];
+    const HTML_NAMED_ENTITIES: &[(&str, char)] = &[
+        ("amp", '&'),
+        ("lt", '<'),
+        ("gt", '>'),
+        ("quot", '"'),
+        ("apos", '\''),
+        ("nbsp", '\u{A0}'),
+        ("copy", '©'),
+        ("reg", '®'),
+        ("trade", '™'),
+        ("hellip", '…'),
+        ("mdash", '—'),
+        ("ndash", '–'),
+        ("lsquo", '‘'),
+        ("rsquo", '’'),
+        ("ldquo", '“'),
+        ("rdquo", '”'),
+        ("laquo", '«'),
+        ("raquo", '»'),
+        ("middot", '·'),
+        ("times", '×'),
+        ("yen", '¥'),
+        ("euro", '€'),
+    ];
+
+    /// htmlのタグを取り除き、エンティティをデコードしたテキストを返す
+    ///
+    /// head, script, style等の内容とコメントは取り除かれる
+    pub fn html_to_text(html: &str, opts: &HtmlToTextOptions) -> String {
+        let mut out = String::with_capacity(html.len());
+        let mut rest = html;
+        let mut skip_until: Option<String> = None;
+        let mut in_pre = false;
+        let push_text = |out: &mut String, text: &str, in_pre: bool| {
+            let text = decode_html_entities(text);
+            if !opts.collapse_whitespace || in_pre {
+                out.push_str(&text);
+                return;
+            }
+            for c in text.chars() {
+                if !c.is_whitespace() {
+                    out.push(c);
+                } else if !(out.is_empty() || out.ends_with(' ') || out.ends_with('\n')) {
+                    out.push(' ');
+                }
+            }
+        };
+        while let Some(lt) = rest.find('<') {
+            if skip_until.is_none() {
+                push_text(&mut out, &rest[..lt], in_pre);
+            }
+            let after = &rest[lt..];
+            if after.starts_with("<!--") {
+                rest = after.find("-->").map(|i| &after[i + 3..]).unwrap_or("");
+                continue;
+            }
+            // not a tag (ex. "a < b")
+            let is_tag = after[1..]
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '/' || c == '!');
+            let Some(gt) = after.find('>').filter(|_| is_tag) else {
+                if skip_until.is_none() {
+                    push_text(&mut out, "<", in_pre);
+                }
+                rest = &after[1..];
+                continue;
+            };
+            let tag = &after[1..gt];
+            rest = &after[gt + 1..];
+            let closing = tag.starts_with('/');
+            let name = tag
+                .trim_start_matches('/')
+                .split(|c: char| c.is_whitespace() || c == '/')
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+            if let Some(skip) = &skip_until {
+                if closing && name == *skip {
+                    skip_until = None;
+                }
+                continue;
+            }
+            if !closing && !tag.ends_with('/') && HTML_SKIP_TAGS.contains(&name.as_str()) {
+                skip_until = Some(name);
+                continue;
+            }
+            if name == "pre" {
+                in_pre = !closing;
+            }
+            if name == "br" || HTML_BLOCK_TAGS.contains(&name.as_str()) {
+                if !opts.block_newlines {
+                    push_text(&mut out, " ", in_pre);
+                } else if name == "br" || !(out.is_empty() || out.ends_with('\n')) {
+                    while out.ends_with(' ') {
+                        out.pop();
+                    }
+                    out.push('\n');
+                }
+            }
+        }
+        if skip_until.is_none() {
+            push_text(&mut out, rest, in_pre);
+        }
+        out.trim().to_string()
+    }
+
+    /// htmlの文字参照 (`&amp;`, `&#39;`, `&#x1F600;` 等) をデコードする (未知の参照はそのまま残す)
+    pub fn decode_html_entities(text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(amp) = rest.find('&') {
+            out.push_str(&rest[..amp]);
+            let after = &rest[amp + 1..];
+            let decoded = after
+                .find(';')
+                .filter(|semi| *semi <= 32)
+                .and_then(|semi| decode_html_entity(&after[..semi]).map(|c| (c, semi)));
+            match decoded {
+                Some((c, semi)) => {
+                    out.push(c);
+                    rest = &after[semi + 1..];
+                }
+                None => {
+                    out.push('&');
+                    rest = after;
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+
+    fn decode_html_entity(name: &str) -> Option<char> {
+        if let Some(hex) = name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+            u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+        } else if let Some(dec) = name.strip_prefix('#') {
+            dec.parse::<u32>().ok().and_then(char::from_u32)
+        } else {
+            HTML_NAMED_ENTITIES
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, c)| *c)
+        }
     }
     /// 文字列を指定された区切り文字または最大長で分割する
     ///
@@ -447,6 +762,167 @@ pub mod text {
         Ok(parts)
     }
 
+    /// split_textの最大長をトークン数で指定する版
+    ///
+    /// # Arguments
+    /// * `text` - 分割する文字列
+    /// * `max_tokens` - 各部分の最大トークン数 (1文字で超える場合はその1文字を1つの部分とする)
+    /// * `delimiters` - 区切り文字（優先順位順）
+    /// * `provider` - トークン数を数えるtokenizer
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>>` - 分割された文字列
+    pub fn split_text_by_tokens(
+        text: &str,
+        max_tokens: usize,
+        delimiters: &[&str],
+        provider: &impl TokenProvider,
+    ) -> Result<Vec<String>> {
+        if max_tokens == 0 {
+            return Err(anyhow!("max_tokens must be greater than 0"));
+        }
+        let mut parts = Vec::new();
+        let mut byte_start = 0;
+
+        while byte_start < text.len() {
+            let rest = &text[byte_start..];
+            let mut byte_end = byte_start + token_prefix_len(rest, max_tokens, provider)?;
+            if byte_end == byte_start {
+                // 1文字で超える場合はその1文字を1つの部分とする
+                byte_end += rest.chars().next().map(char::len_utf8).unwrap_or_default();
+            }
+
+            // 区切り文字による分割を試みる
+            let mut split_end = byte_end;
+            if byte_end < text.len() {
+                let substr = &text[byte_start..byte_end];
+                for delimiter in delimiters {
+                    if let Some(last_pos) = substr.rfind(delimiter) {
+                        split_end = byte_start + last_pos + delimiter.len();
+                        break;
+                    }
+                }
+            }
+            parts.push(text[byte_start..split_end].to_string());
+            byte_start = split_end;
+        }
+
+        Ok(parts)
+    }
+
+    // 1トークンあたりの文字数の目安 (token_prefix_lenで最初にトークン化する先頭部分の長さ)
+    const CHARS_PER_TOKEN_HINT: usize = 4;
+
+    // max_tokensに収まる最長の先頭部分のバイト長 (トークン数は長さに対して単調増加とみなして二分探索)
+    // 長いテキストの全体をトークン化しないように、先頭から調べる範囲を収まる限り倍々に広げる
+    fn token_prefix_len(
+        text: &str,
+        max_tokens: usize,
+        provider: &impl TokenProvider,
+    ) -> Result<usize> {
+        // 先頭からlo文字は収まる
+        let mut lo = 0;
+        let mut window = max_tokens.saturating_mul(CHARS_PER_TOKEN_HINT).max(1);
+        loop {
+            let char_byte_positions: Vec<usize> = text
+                .char_indices()
+                .map(|(byte_pos, _)| byte_pos)
+                .take(window + 1)
+                .collect();
+            let byte_pos = |n: usize| char_byte_positions.get(n).copied().unwrap_or(text.len());
+            let whole = char_byte_positions.len() <= window;
+            let limit = if whole {
+                char_byte_positions.len()
+            } else {
+                window
+            };
+            if provider.estimate_token_count(&text[..byte_pos(limit)])? <= max_tokens {
+                if whole {
+                    return Ok(text.len());
+                }
+                lo = limit;
+                window = window.saturating_mul(2);
+                continue;
+            }
+            // 先頭からlimit文字は収まらない (空文字列も収まらない場合はlimit == 0)
+            if limit == 0 {
+                return Ok(0);
+            }
+            let mut hi = limit - 1;
+            while lo < hi {
+                let mid = lo + (hi - lo).div_ceil(2);
+                if provider.estimate_token_count(&text[..byte_pos(mid)])? <= max_tokens {
+                    lo = mid;
+                } else {
+                    hi = mid - 1;
+                }
+            }
+            return Ok(byte_pos(lo));
+        }
+    }
+
+    /// 文字列を最大文字数で切り詰める (マルチバイト文字を分割しない)
+    pub fn truncate_chars(s: &str, max_chars: usize) -> &str {
+        s.char_indices()
+            .nth(max_chars)
+            .map(|(i, _)| &s[..i])
+            .unwrap_or(s)
+    }
+    /// truncate_charsで切り詰めた場合は末尾にellipsisを付ける (ellipsisを含めてmax_chars以内)
+    pub fn truncate_chars_with_ellipsis(s: &str, max_chars: usize, ellipsis: &str) -> String {
+        if truncate_chars(s, max_chars).len() == s.len() {
+            return s.to_string();
+        }
+        let keep = max_chars.saturating_sub(ellipsis.chars().count());
+        format!("{}{}", truncate_chars(s, keep), ellipsis)
+    }
+
+    /// 文字列を最大書記素クラスタ数で切り詰める (結合文字や絵文字の合字を分割しない)
+    pub fn truncate_graphemes(s: &str, max_graphemes: usize) -> &str {
+        s.grapheme_indices(true)
+            .nth(max_graphemes)
+            .map(|(i, _)| &s[..i])
+            .unwrap_or(s)
+    }
+    /// truncate_graphemesで切り詰めた場合は末尾にellipsisを付ける (ellipsisを含めてmax_graphemes以内)
+    pub fn truncate_graphemes_with_ellipsis(
+        s: &str,
+        max_graphemes: usize,
+        ellipsis: &str,
+    ) -> String {
+        if truncate_graphemes(s, max_graphemes).len() == s.len() {
+            return s.to_string();
+        }
+        let keep = max_graphemes.saturating_sub(ellipsis.graphemes(true).count());
+        format!("{}{}", truncate_graphemes(s, keep), ellipsis)
+    }
+
+    /// 文字列を最大トークン数で切り詰める (文字単位で切り詰める)
+    pub fn truncate_tokens<'a>(
+        s: &'a str,
+        max_tokens: usize,
+        provider: &impl TokenProvider,
+    ) -> Result<&'a str> {
+        Ok(&s[..token_prefix_len(s, max_tokens, provider)?])
+    }
+    /// truncate_tokensで切り詰めた場合は末尾にellipsisを付ける (ellipsisを含めてmax_tokens以内)
+    pub fn truncate_tokens_with_ellipsis(
+        s: &str,
+        max_tokens: usize,
+        ellipsis: &str,
+        provider: &impl TokenProvider,
+    ) -> Result<String> {
+        if token_prefix_len(s, max_tokens, provider)? == s.len() {
+            return Ok(s.to_string());
+        }
+        let keep = max_tokens.saturating_sub(provider.estimate_token_count(ellipsis)?);
+        Ok(format!(
+            "{}{}",
+            truncate_tokens(s, keep, provider)?,
+            ellipsis
+        ))
+    }
+
     // create test for extract_url_simple
     #[cfg(test)]
     mod tests {
@@ -475,6 +951,74 @@ pub mod text {
             let mes = format!("\"<\"{}\">\"", url);
             assert_eq!(extract_url_simple(&mes), Some(url));
         }
+        #[test]
+        fn test_extract_urls() {
+            let mes = "see https://example.com/a. and http://example.org/b?q=1, ok";
+            assert_eq!(
+                extract_urls(mes),
+                vec![
+                    (4..25, "https://example.com/a"),
+                    (31..55, "http://example.org/b?q=1")
+                ]
+            );
+            assert_eq!(
+                extract_urls_with_options(mes, true),
+                vec![
+                    (4..26, "https://example.com/a."),
+                    (31..56, "http://example.org/b?q=1,")
+                ]
+            );
+            assert!(extract_urls("no url here").is_empty());
+        }
+
+        #[test]
+        fn test_strip_markdown() {
+            let md = r#"# Title
+
+> **Note**: see [the docs](https://example.com/docs "docs") and ![logo](logo.png)
+
+- item with `code` and *emphasis*
+- snake_case_name and _underscored_ ~~old~~
+
+```rust
+fn main() {}
+```
+---
+[ref]: https://example.com/ref
+"#;
+            assert_eq!(
+                strip_markdown(md),
+                "Title\n\nNote: see the docs and \n\nitem with code and emphasis\nsnake_case_name and underscored old"
+            );
+            assert_eq!(
+                strip_markdown_with_options("[the docs](https://example.com/docs)", true),
+                "https://example.com/docs"
+            );
+        }
+
+        #[test]
+        fn test_html_to_text() {
+            let html = "<html><head><title>T</title><style>p{}</style></head><body>\
+                <h1>Title</h1><p>Hello,&nbsp;&lt;world&gt; &amp;\n  friends &#x1F600;&#33;</p>\
+                <!-- comment --><ul><li>one </li><li>two</li></ul>a<br>b &unknown; 1 < 2\
+                <script>var x = '<p>';</script><pre>  keep\n  spaces</pre></body></html>";
+            assert_eq!(
+                html_to_text(html, &HtmlToTextOptions::default()),
+                "Title\nHello, <world> & friends 😀!\none\ntwo\na\nb &unknown; 1 < 2\n  keep\n  spaces"
+            );
+            let opts = HtmlToTextOptions {
+                block_newlines: false,
+                ..Default::default()
+            };
+            assert_eq!(
+                html_to_text("<p>one</p><p>two<br/>three</p>", &opts),
+                "one two three"
+            );
+            assert_eq!(
+                decode_html_entities("&quot;a&quot; &#39;b&#39; & c"),
+                "\"a\" 'b' & c"
+            );
+        }
 
         #[test]
         fn test_split_japanese_text() -> Result<()> {
@@ -494,6 +1038,108 @@ pub mod text {
             Ok(())
         }
 
+        // 1 token per whitespace separated word
+        struct WordTokenizer;
+        impl TokenProvider for WordTokenizer {
+            fn tokenize(&self, text: &str) -> Result<Vec<u32>> {
+                Ok(text.split_whitespace().map(|_| 0).collect())
+            }
+        }
+        // 1 token per char
+        struct CharTokenizer;
+        impl TokenProvider for CharTokenizer {
+            fn tokenize(&self, text: &str) -> Result<Vec<u32>> {
+                Ok(text.chars().map(|c| c as u32).collect())
+            }
+        }
+
+        #[test]
+        fn test_split_text_by_tokens() -> Result<()> {
+            let text = "a b c. d e f. g";
+            assert_eq!(
+                split_text_by_tokens(text, 4, &["."], &WordTokenizer)?,
+                vec!["a b c.", " d e f. g"]
+            );
+            assert_eq!(
+                split_text_by_tokens(text, 2, &[], &WordTokenizer)?,
+                vec!["a b ", "c. d ", "e f. ", "g"]
+            );
+            assert!(split_text_by_tokens(text, 0, &[], &WordTokenizer).is_err());
+
+            // same as split_text if 1 token per char
+            let text = "吾輩は猫である。名前はまだ無い。どこで生れたかとんと見当がつかぬ。";
+            let delimiters = &["。", "、"];
+            assert_eq!(
+                split_text_by_tokens(text, 10, delimiters, &CharTokenizer)?,
+                split_text(text, 10, delimiters)?
+            );
+
+            // tokenize only around each part (not the whole rest of the text)
+            struct CountingTokenizer(std::cell::Cell<usize>);
+            impl TokenProvider for CountingTokenizer {
+                fn tokenize(&self, text: &str) -> Result<Vec<u32>> {
+                    self.0.set(self.0.get() + text.len());
+                    CharTokenizer.tokenize(text)
+                }
+            }
+            let text = "a".repeat(10000);
+            let tokenizer = CountingTokenizer(std::cell::Cell::new(0));
+            let parts = split_text_by_tokens(&text, 10, &[], &tokenizer)?;
+            assert_eq!(parts.len(), 1000);
+            assert!(tokenizer.0.get() < 500_000);
+            Ok(())
+        }
+
+        #[test]
+        fn test_tokens_with_special_token() -> Result<()> {
+            // counts a BOS token even for empty text
+            struct BosTokenizer;
+            impl TokenProvider for BosTokenizer {
+                fn tokenize(&self, text: &str) -> Result<Vec<u32>> {
+                    Ok(std::iter::once(0)
+                        .chain(text.chars().map(|c| c as u32))
+                        .collect())
+                }
+            }
+            assert_eq!(truncate_tokens("", 0, &BosTokenizer)?, "");
+            assert_eq!(truncate_tokens("abc", 0, &BosTokenizer)?, "");
+            assert_eq!(truncate_tokens("abc", 3, &BosTokenizer)?, "ab");
+            assert_eq!(
+                split_text_by_tokens("abc", 1, &[], &BosTokenizer)?,
+                vec!["a", "b", "c"]
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn test_truncate() -> Result<()> {
+            let text = "吾輩は猫である。";
+            assert_eq!(truncate_chars(text, 3), "吾輩は");
+            assert_eq!(truncate_chars(text, 100), text);
+            assert_eq!(truncate_chars_with_ellipsis(text, 4, "…"), "吾輩は…");
+            assert_eq!(truncate_chars_with_ellipsis(text, 8, "…"), text);
+
+            let text = "e\u{301}👨\u{200D}👩\u{200D}👧abc";
+            assert_eq!(
+                truncate_graphemes(text, 2),
+                "e\u{301}👨\u{200D}👩\u{200D}👧"
+            );
+            assert_eq!(
+                truncate_graphemes_with_ellipsis(text, 4, "..."),
+                "e\u{301}..."
+            );
+            assert_eq!(truncate_graphemes_with_ellipsis(text, 5, "..."), text);
+
+            let text = "a b c d e";
+            assert_eq!(truncate_tokens(text, 3, &WordTokenizer)?, "a b c ");
+            assert_eq!(truncate_tokens(text, 5, &WordTokenizer)?, text);
+            assert_eq!(
+                truncate_tokens_with_ellipsis(text, 3, "…", &WordTokenizer)?,
+                "a b …"
+            );
+            Ok(())
+        }
+
         #[test]
         fn test_split_by_length() -> Result<()> {
             let text = "あいうiえお😁かきくjけこ🤨さしすkせそ.";
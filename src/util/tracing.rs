@@ -1,8 +1,11 @@
 use crate::util::id_generator::iputil;
 use anyhow::{anyhow, Context, Result};
+use once_cell::sync::Lazy;
 use opentelemetry::KeyValue;
 use opentelemetry::{global, trace::TracerProvider};
+use opentelemetry_otlp::TonicExporterBuilder;
 use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
 use opentelemetry_sdk::propagation::TraceContextPropagator;
 use opentelemetry_sdk::trace::{BatchConfig, Tracer};
 use opentelemetry_sdk::{runtime, trace as sdktrace, Resource};
@@ -12,19 +15,56 @@ use opentelemetry_semantic_conventions::{
 };
 use serde::Deserialize;
 use std::env;
-use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Mutex;
 use std::time::Duration;
+use tonic::metadata::{MetadataKey, MetadataMap};
 use tracing::Subscriber;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::fmt::Layer;
 use tracing_subscriber::layer::SubscriberExt;
-use tracing_subscriber::{filter, prelude::*};
+use tracing_subscriber::{reload, EnvFilter, Registry};
 
 use super::result::ToOption;
 
 const APP_SERVICE_NAME: &str = env!("CARGO_PKG_NAME");
 
+// flush buffered logs of the non-blocking file writer on drop
+static LOG_WRITER_GUARD: Lazy<Mutex<Option<WorkerGuard>>> = Lazy::new(|| Mutex::new(None));
+// handle to change the log filter of the subscriber at runtime
+static LOG_FILTER_HANDLE: Lazy<Mutex<Option<reload::Handle<EnvFilter, Registry>>>> =
+    Lazy::new(|| Mutex::new(None));
+// shut down (and flush) in shutdown_tracer_provider
+static METER_PROVIDER: Lazy<Mutex<Option<SdkMeterProvider>>> = Lazy::new(|| Mutex::new(None));
+
+/// rotation period of the log file (LOG_ROTATION=minutely|hourly|daily|never)
+///
+/// rotated files are named `{file_name}.{date}` (no rotation: `{file_name}`)
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    Daily,
+    #[default]
+    Never,
+}
+
+impl From<LogRotation> for Rotation {
+    fn from(rotation: LogRotation) -> Self {
+        match rotation {
+            LogRotation::Minutely => Rotation::MINUTELY,
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        }
+    }
+}
+
 #[derive(Deserialize, Debug)]
 pub struct LoggingConfig {
     pub app_name: Option<String>,
@@ -33,6 +73,13 @@ pub struct LoggingConfig {
     pub file_dir: Option<String>,
     pub use_json: bool,
     pub use_stdout: bool,
+    #[serde(default)]
+    pub rotation: LogRotation,
+    /// keep only the latest n rotated log files (keep all if not specified)
+    pub max_log_files: Option<usize>,
+    /// write log file in a background thread (buffered logs are flushed by `shutdown_tracer_provider`)
+    #[serde(default)]
+    pub non_blocking: bool,
 }
 
 impl LoggingConfig {
@@ -44,6 +91,9 @@ impl LoggingConfig {
             file_dir: None,
             use_json: false,
             use_stdout: true,
+            rotation: LogRotation::Never,
+            max_log_files: None,
+            non_blocking: false,
         }
     }
 }
@@ -71,6 +121,15 @@ pub async fn init_from_env_and_filename(
 
 pub fn shutdown_tracer_provider() {
     opentelemetry::global::shutdown_tracer_provider();
+    if let Some(provider) = METER_PROVIDER.lock().ok().and_then(|mut p| p.take()) {
+        if let Err(e) = provider.shutdown() {
+            println!("failed to shutdown meter provider: {:?}", e);
+        }
+    }
+    // drop the guard to flush the non-blocking log writer
+    if let Ok(mut guard) = LOG_WRITER_GUARD.lock() {
+        guard.take();
+    }
 }
 
 pub fn create_filename_with_ip_postfix(
@@ -147,70 +206,164 @@ fn resource() -> opentelemetry_sdk::Resource {
         SCHEMA_URL,
     )
 }
+/// OTLP exporter settings shared by the trace and metric exporters
+///
+/// only the grpc (tonic) protocol is supported (no HTTP/protobuf) and logs are not exported by OTLP
+///
+/// - OTLP_ADDR: collector endpoint (exporters are not set up if not specified)
+/// - OTLP_TIMEOUT_SEC: export timeout (default: 10)
+/// - OTLP_AUTH_TOKEN: sent as `authorization: Bearer {token}`
+/// - OTLP_HEADERS: additional metadata (ex. "x-tenant=foo,x-env=dev")
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct OtlpExporterConfig {
+    endpoint: String,
+    timeout: Duration,
+    auth_token: Option<String>,
+    headers: Vec<(String, String)>,
+}
+
+impl OtlpExporterConfig {
+    fn from_env() -> Result<Option<Self>> {
+        let Ok(endpoint) = env::var("OTLP_ADDR") else {
+            // not specified
+            return Ok(None);
+        };
+        let timeout = match env::var("OTLP_TIMEOUT_SEC") {
+            Ok(sec) => Duration::from_secs(
+                sec.parse()
+                    .with_context(|| format!("invalid OTLP_TIMEOUT_SEC: {}", sec))?,
+            ),
+            Err(_) => Duration::from_secs(10),
+        };
+        let headers = env::var("OTLP_HEADERS")
+            .map(|h| Self::parse_headers(&h))
+            .unwrap_or_else(|_| Ok(Vec::new()))?;
+        Ok(Some(Self {
+            endpoint,
+            timeout,
+            auth_token: env::var("OTLP_AUTH_TOKEN").ok().filter(|t| !t.is_empty()),
+            headers,
+        }))
+    }
+    fn parse_headers(headers: &str) -> Result<Vec<(String, String)>> {
+        headers
+            .split(',')
+            .map(str::trim)
+            .filter(|h| !h.is_empty())
+            .map(|h| {
+                h.split_once('=')
+                    .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                    .ok_or_else(|| {
+                        anyhow!("invalid OTLP_HEADERS entry (expected key=value): {}", h)
+                    })
+            })
+            .collect()
+    }
+    fn exporter(&self) -> Result<TonicExporterBuilder> {
+        let mut metadata = MetadataMap::new();
+        if let Some(token) = &self.auth_token {
+            metadata.insert(
+                "authorization",
+                format!("Bearer {}", token)
+                    .parse()
+                    .context("invalid OTLP_AUTH_TOKEN")?,
+            );
+        }
+        for (k, v) in &self.headers {
+            metadata.insert(
+                MetadataKey::from_bytes(k.as_bytes())
+                    .with_context(|| format!("invalid OTLP_HEADERS key: {}", k))?,
+                v.parse()
+                    .with_context(|| format!("invalid OTLP_HEADERS value: {}", v))?,
+            );
+        }
+        Ok(opentelemetry_otlp::new_exporter()
+            .tonic()
+            .with_endpoint(&self.endpoint)
+            .with_timeout(self.timeout)
+            .with_metadata(metadata))
+    }
+}
+
 async fn otlp_tracer_from_env(app_service_name: String) -> Result<Option<Tracer>> {
-    let addr: Result<String> = env::var("OTLP_ADDR").context("otlp addr");
-    match addr {
-        Ok(addr) => {
-            println!("otlp addr: {:?}", &addr);
-            match opentelemetry_otlp::new_pipeline()
-                .tracing()
-                .with_trace_config(
-                    opentelemetry_sdk::trace::Config::default()
-                        // Customize sampling strategy
-                        .with_sampler(opentelemetry_sdk::trace::Sampler::ParentBased(Box::new(
-                            opentelemetry_sdk::trace::Sampler::TraceIdRatioBased(1.0),
-                        )))
-                        // If export trace to AWS X-Ray, you can use XrayIdGenerator
-                        .with_id_generator(opentelemetry_sdk::trace::RandomIdGenerator::default())
-                        .with_resource(resource()),
-                )
-                .with_batch_config(BatchConfig::default())
-                .with_exporter(
-                    opentelemetry_otlp::new_exporter()
-                        .tonic()
-                        .with_endpoint(&addr)
-                        .with_timeout(Duration::from_secs(10)),
-                )
-                .install_batch(opentelemetry_sdk::runtime::Tokio)
-            {
-                Ok(tr) => {
-                    global::set_text_map_propagator(TraceContextPropagator::new());
-                    Ok(Some(tr.tracer(app_service_name.clone())))
-                }
-                Err(e) => {
-                    println!("failed to install otlp tracer: {:?}", e);
-                    Err(e.into())
-                }
-            }
+    let Some(config) = OtlpExporterConfig::from_env()? else {
+        return Ok(None);
+    };
+    println!("otlp addr: {:?}", &config.endpoint);
+    match opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_trace_config(
+            opentelemetry_sdk::trace::Config::default()
+                // Customize sampling strategy
+                .with_sampler(opentelemetry_sdk::trace::Sampler::ParentBased(Box::new(
+                    opentelemetry_sdk::trace::Sampler::TraceIdRatioBased(1.0),
+                )))
+                // If export trace to AWS X-Ray, you can use XrayIdGenerator
+                .with_id_generator(opentelemetry_sdk::trace::RandomIdGenerator::default())
+                .with_resource(resource()),
+        )
+        .with_batch_config(BatchConfig::default())
+        .with_exporter(config.exporter()?)
+        .install_batch(opentelemetry_sdk::runtime::Tokio)
+    {
+        Ok(tr) => {
+            global::set_text_map_propagator(TraceContextPropagator::new());
+            Ok(Some(tr.tracer(app_service_name.clone())))
         }
-        Err(_e) => {
-            // not specified
-            Ok(None)
+        Err(e) => {
+            println!("failed to install otlp tracer: {:?}", e);
+            Err(e.into())
         }
     }
 }
 
+/// set the global meter provider exporting metrics to the OTLP (grpc) collector
+/// with the same settings as traces (see `OtlpExporterConfig`)
+///
+/// must be called in a tokio runtime (the periodic exporter runs on it). returns an error otherwise,
+/// and false if OTLP_ADDR is not specified
+pub fn set_otlp_meter_provider_from_env() -> Result<bool> {
+    let Some(config) = OtlpExporterConfig::from_env()? else {
+        return Ok(false);
+    };
+    tokio::runtime::Handle::try_current()
+        .map_err(|e| anyhow!("otlp meter provider requires a tokio runtime: {:?}", e))?;
+    let provider = opentelemetry_otlp::new_pipeline()
+        .metrics(runtime::Tokio)
+        .with_exporter(config.exporter()?)
+        .with_resource(resource())
+        .build()
+        .map_err(|e| anyhow!("failed to build otlp meter provider: {:?}", e))?;
+    global::set_meter_provider(provider.clone());
+    if let Ok(mut p) = METER_PROVIDER.lock() {
+        *p = Some(provider);
+    }
+    Ok(true)
+}
+
 pub async fn setup_layer_from_logging_config(
     conf: &LoggingConfig,
 ) -> Result<Box<dyn Subscriber + Send + Sync + 'static>> {
     let lv = tracing::Level::from_str(conf.level.as_ref().unwrap_or(&"INFO".to_string()).as_str())
         .unwrap_or(tracing::Level::INFO);
-    let filter = filter::Targets::new().with_default(lv);
+    let (filter, filter_handle) = reload::Layer::new(level_filter(lv));
+    if let Ok(mut handle) = LOG_FILTER_HANDLE.lock() {
+        *handle = Some(filter_handle);
+    }
     let dir = conf
         .file_dir
         .as_ref()
         .map(|d| PathBuf::from_str(d).context("Invalid log file directory"))
         .unwrap_or(env::current_dir().map_err(|e| e.into()))?;
 
-    let create_file_fn = || {
-        if let Some(file_name) = conf.file_name.as_deref() {
-            std::fs::create_dir_all(&dir).expect("create log file directory:");
-            Some(File::create(dir.join(file_name)).unwrap_or_else(|_| {
-                panic!("create log file to {:?}:", dir.join(file_name).as_os_str())
-            }))
-        } else {
-            None
-        }
+    let file_writer = match conf.file_name.as_deref() {
+        Some(file_name) => Some(create_file_writer(conf, &dir, file_name)?),
+        None => None,
+    };
+    let (json_file_writer, file_writer) = if conf.use_json {
+        (file_writer, None)
+    } else {
+        (None, file_writer)
     };
     let app_service_name = conf
         .app_name
@@ -227,25 +380,8 @@ pub async fn setup_layer_from_logging_config(
     let subscriber = Box::new(
         tracing_subscriber::registry()
             .with(filter)
-            .with(match create_file_fn() {
-                // for json case
-                Some(f) if conf.use_json => Some(
-                    Layer::new()
-                        .with_writer(f.with_max_level(lv))
-                        .with_ansi(false)
-                        .json(),
-                ),
-                _ => None,
-            })
-            .with(match create_file_fn() {
-                // for not json case
-                Some(f) if !conf.use_json => Some(
-                    Layer::new()
-                        .with_writer(f.with_max_level(lv))
-                        .with_ansi(false),
-                ),
-                _ => None,
-            })
+            .with(json_file_writer.map(|w| Layer::new().with_writer(w).with_ansi(false).json()))
+            .with(file_writer.map(|w| Layer::new().with_writer(w).with_ansi(false)))
             .with(remote_tracer.map(|t| tracing_opentelemetry::layer().with_tracer(t)))
             .with(if !conf.use_json && conf.use_stdout {
                 Some(tracing_subscriber::fmt::layer().pretty())
@@ -265,7 +401,87 @@ pub async fn setup_layer_from_logging_config(
     Ok(subscriber)
 }
 
+/// change the log level of the subscriber set up by `setup_layer_from_logging_config` at runtime
+pub fn set_log_level(level: &str) -> Result<()> {
+    let lv = tracing::Level::from_str(level)
+        .map_err(|e| anyhow!("invalid log level {}: {}", level, e))?;
+    reload_log_filter(level_filter(lv))
+}
+
+/// change the log filter at runtime with `EnvFilter` directives (same syntax as RUST_LOG)
+/// (ex. "info,h2=warn,my_crate::db=debug")
+pub fn set_env_filter(directives: &str) -> Result<()> {
+    let filter = EnvFilter::try_new(directives)
+        .map_err(|e| anyhow!("invalid log filter {}: {}", directives, e))?;
+    reload_log_filter(filter)
+}
+
+fn level_filter(level: tracing::Level) -> EnvFilter {
+    EnvFilter::default().add_directive(LevelFilter::from_level(level).into())
+}
+
+fn reload_log_filter(filter: EnvFilter) -> Result<()> {
+    let handle = LOG_FILTER_HANDLE
+        .lock()
+        .map_err(|e| anyhow!("failed to lock log filter handle: {:?}", e))?
+        .clone()
+        .ok_or_else(|| anyhow!("logging is not set up by setup_layer_from_logging_config"))?;
+    handle
+        .reload(filter)
+        .map_err(|e| anyhow!("failed to reload log filter: {:?}", e))
+}
+
+// log file appender (appends to existing file, rotated by conf.rotation)
+fn create_file_writer(conf: &LoggingConfig, dir: &Path, file_name: &str) -> Result<BoxMakeWriter> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("create log file directory: {:?}", dir.as_os_str()))?;
+    let mut builder = RollingFileAppender::builder()
+        .rotation(conf.rotation.into())
+        .filename_prefix(file_name);
+    if let Some(max) = conf.max_log_files {
+        builder = builder.max_log_files(max);
+    }
+    let appender = builder
+        .build(dir)
+        .with_context(|| format!("create log file to {:?}", dir.join(file_name).as_os_str()))?;
+    if conf.non_blocking {
+        let (writer, guard) = tracing_appender::non_blocking(appender);
+        if let Ok(mut g) = LOG_WRITER_GUARD.lock() {
+            // previous guard (if any) is dropped and flushed here
+            *g = Some(guard);
+        }
+        Ok(BoxMakeWriter::new(writer))
+    } else {
+        Ok(BoxMakeWriter::new(appender))
+    }
+}
+
 // for simple stdout logging
 pub fn tracing_init_test(level: tracing::Level) {
     tracing_subscriber::fmt().with_max_level(level).init();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_otlp_headers() -> Result<()> {
+        assert_eq!(
+            OtlpExporterConfig::parse_headers("x-tenant=foo, x-env = dev,")?,
+            vec![
+                ("x-tenant".to_string(), "foo".to_string()),
+                ("x-env".to_string(), "dev".to_string())
+            ]
+        );
+        assert!(OtlpExporterConfig::parse_headers("").unwrap().is_empty());
+        assert!(OtlpExporterConfig::parse_headers("x-tenant").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_log_level_invalid() {
+        assert!(set_log_level("verbose").is_err());
+        assert!(set_env_filter("my_crate=loud").is_err());
+    }
+}